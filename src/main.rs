@@ -1,3 +1,4 @@
+extern crate num;
 extern crate sdl2;
 extern crate sdl2_image;
 extern crate sdl2_ttf;
@@ -11,10 +12,77 @@ mod phi;
 mod views;
 
 fn main() {
+    // `--record <file>` saves the session to a replay file, which
+    // `--replay <file>` plays back, to reproduce what testers saw.
+    // `--record-demo <file>` only saves the first game, from its start, as
+    // a demo for the main menu such as `assets/demo.replay`.
+    // `--capture <dir>` saves every frame to `dir`, e.g. while a replay is
+    // played to make a trailer.
+    // `--golden` compares frames of the views to the references stored in
+    // `assets/golden`, and `--golden-update` overwrites them.
+    let args: Vec<String> = ::std::env::args().collect();
+    let option = |name: &str| args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .cloned();
+
+    let update = args.iter().any(|arg| arg == "--golden-update");
+    if update || args.iter().any(|arg| arg == "--golden") {
+        let passed = ::phi::golden::run(&golden_cases(), ::phi::golden::GOLDEN_DIR, update);
+        ::std::process::exit(if passed { 0 } else { 1 });
+    }
+
     ::phi::spawn("ArcadeRS Shooter", |phi| {
+        if let Some(path) = option("--record") {
+            if let Err(e) = phi.start_recording(&path) {
+                println!("Could not record to {}: {}", path, e);
+            }
+        }
+        phi.demo_recording = option("--record-demo");
+        if let Some(path) = option("--replay") {
+            if !phi.play_replay(&path) {
+                println!("Could not load the replay {}", path);
+            }
+        }
+
+        if let Some(dir) = option("--capture") {
+            if let Err(e) = phi.capture.start_sequence(&dir, 1) {
+                println!("Could not capture to {}: {}", dir, e);
+            }
+        }
+
         Box::new(::views::main_menu::MainMenuView::new(phi))
     });
 }
+
+/// The frames checked by `--golden`. Add a case, or a tick, whenever a
+/// visual bug is fixed, so that it does not come back.
+///
+/// A case is only listed once its references were written with
+/// `--golden-update` and committed, as a case without references fails.
+/// `assets/golden/ship_edges.replay` is ready for the first one: it pushes
+/// the ship against the top edge, then into the top-left, bottom-left and
+/// bottom-right corners of its region, at ticks 60, 120, 330 and 560:
+///
+/// ```text
+/// GoldenCase::new("ship_edges", vec![60, 120, 330, 560],
+///     Box::new(|phi| Box::new(::views::game::ShipView::new(phi))))
+///     .scripted("assets/golden/ship_edges.replay")
+/// ```
+fn golden_cases() -> Vec<::phi::golden::GoldenCase> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    /// Needs a video driver, so it only runs when asked for, with
+    /// `cargo test -- --ignored golden`.
+    #[test]
+    #[ignore]
+    fn golden() {
+        assert!(::phi::golden::run(&::golden_cases(), ::phi::golden::GOLDEN_DIR, false));
+    }
+}
 //     // Init SDL2
 //     let sdl_context = sdl2::init().unwrap();
 //     let video = sdl_context.video().unwrap();
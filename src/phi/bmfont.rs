@@ -0,0 +1,251 @@
+use ::phi::data::Rectangle;
+use ::phi::gfx::Sprite;
+use ::phi::text::{GlyphQuad, GlyphSource};
+
+use ::std::collections::hash_map::HashMap;
+use ::std::fs::File;
+use ::std::io::Read;
+use ::std::path::Path;
+
+use ::sdl2::render::Renderer;
+
+/// A glyph as described by a `char` line of a BMFont descriptor, at the
+/// size the font was generated at.
+struct BitmapGlyph {
+	/// `None` for glyphs with nothing to draw, such as the space, which are
+	/// only kept for their advance and kerning.
+	sprite: Option<Sprite>,
+	/// The size of the glyph, at the size the font was generated at.
+	size: (f64, f64),
+	xoffset: f64,
+	yoffset: f64,
+	xadvance: f64,
+}
+
+/// A bitmap font in the AngelCode BMFont text format: a `.fnt` descriptor
+/// listing the position of every character in one or more PNG pages.
+///
+/// It implements `GlyphSource`, so that it is laid out and drawn exactly like
+/// a TrueType font. Glyphs are scaled so that the font's nominal size matches
+/// the requested one.
+pub struct BitmapFont {
+	glyphs: HashMap<char, BitmapGlyph>,
+	kerning: HashMap<(char, char), f64>,
+	line_height: f64,
+	/// The size, in pixels, which the font was generated at.
+	nominal_size: f64,
+	scale: f64,
+}
+
+impl BitmapFont {
+	/// Loads the descriptor at `path` along with its pages, which are looked
+	/// up relative to the descriptor. Returns `None` if any file is missing
+	/// or if the descriptor is not in the text format.
+	pub fn load(renderer: &Renderer, path: &str) -> Option<BitmapFont> {
+		let mut source = String::new();
+		if File::open(path).and_then(|mut file| file.read_to_string(&mut source)).is_err() {
+			return None;
+		}
+
+		let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+		let mut pages = HashMap::new();
+		let mut font = BitmapFont {
+			glyphs: HashMap::new(),
+			kerning: HashMap::new(),
+			line_height: 0.0,
+			nominal_size: 0.0,
+			scale: 1.0,
+		};
+		let mut nominal_size = 0.0;
+
+		for line in source.lines() {
+			let (tag, attrs) = parse_line(line);
+
+			match tag {
+				"info" => {
+					//? A negative size means that it was matched against the
+					//? height of the characters rather than of the cells.
+					nominal_size = attr_f64(&attrs, "size").abs();
+				},
+				"common" => {
+					font.line_height = attr_f64(&attrs, "lineHeight");
+				},
+				"page" => {
+					let file = match attrs.get("file") {
+						Some(file) => dir.join(file),
+						None => return None,
+					};
+					let sprite = match file.to_str().and_then(|file| Sprite::load(renderer, file)) {
+						Some(sprite) => sprite,
+						None => return None,
+					};
+					pages.insert(attr_f64(&attrs, "id") as u32, sprite);
+				},
+				"char" => {
+					let ch = match ::std::char::from_u32(attr_f64(&attrs, "id") as u32) {
+						Some(ch) => ch,
+						None => continue,
+					};
+					let page = match pages.get(&(attr_f64(&attrs, "page") as u32)) {
+						Some(page) => page,
+						None => continue,
+					};
+					let region = Rectangle {
+						x: attr_f64(&attrs, "x"),
+						y: attr_f64(&attrs, "y"),
+						w: attr_f64(&attrs, "width"),
+						h: attr_f64(&attrs, "height"),
+					};
+					//? An empty region would make `Sprite::render` copy the
+					//? whole page over the whole screen.
+					let sprite = if region.w >= 1.0 && region.h >= 1.0 {
+						match page.region(region) {
+							Some(sprite) => Some(sprite),
+							None => continue,
+						}
+					} else {
+						None
+					};
+
+					font.glyphs.insert(ch, BitmapGlyph {
+						sprite: sprite,
+						size: (region.w, region.h),
+						xoffset: attr_f64(&attrs, "xoffset"),
+						yoffset: attr_f64(&attrs, "yoffset"),
+						xadvance: attr_f64(&attrs, "xadvance"),
+					});
+				},
+				"kerning" => {
+					let first = ::std::char::from_u32(attr_f64(&attrs, "first") as u32);
+					let second = ::std::char::from_u32(attr_f64(&attrs, "second") as u32);
+					if let (Some(first), Some(second)) = (first, second) {
+						font.kerning.insert((first, second), attr_f64(&attrs, "amount"));
+					}
+				},
+				_ => {}
+			}
+		}
+
+		//? Fonts generated without an `info` line are assumed to have been
+		//? generated at their line height.
+		font.nominal_size = if nominal_size > 0.0 { nominal_size } else { font.line_height };
+		Some(font)
+	}
+
+	/// Scales the glyphs so that text is drawn at `size` pixels, the same way
+	/// as a TrueType font loaded at that size.
+	pub fn set_size(&mut self, size: i32) {
+		if self.nominal_size > 0.0 {
+			self.scale = size as f64 / self.nominal_size;
+		}
+	}
+}
+
+impl GlyphSource for BitmapFont {
+	fn line_height(&self) -> f64 {
+		self.line_height * self.scale
+	}
+
+	fn advance(&mut self, ch: char) -> Option<f64> {
+		self.glyphs.get(&ch).map(|glyph| glyph.xadvance * self.scale)
+	}
+
+	fn glyph(&mut self, _: &Renderer, ch: char) -> Option<GlyphQuad> {
+		let scale = self.scale;
+
+		self.glyphs.get(&ch).map(|glyph| {
+			let (w, h) = glyph.size;
+			GlyphQuad {
+				sprite: glyph.sprite.clone(),
+				dest: Rectangle {
+					x: glyph.xoffset * scale,
+					y: glyph.yoffset * scale,
+					w: w * scale,
+					h: h * scale,
+				},
+				advance: glyph.xadvance * scale,
+			}
+		})
+	}
+
+	fn kerning(&mut self, left: char, right: char) -> f64 {
+		self.kerning.get(&(left, right)).map(|amount| amount * self.scale).unwrap_or(0.0)
+	}
+}
+
+/// Splits a descriptor line such as `char id=65 x=2 file="a b.png"` into its
+/// tag and its attributes. Quoted values may contain spaces.
+fn parse_line(line: &str) -> (&str, HashMap<String, String>) {
+	let line = line.trim();
+	let (tag, mut rest) = match line.find(' ') {
+		Some(i) => (&line[..i], &line[i..]),
+		None => (line, ""),
+	};
+	let mut attrs = HashMap::new();
+
+	loop {
+		rest = rest.trim_left();
+		let eq = match rest.find('=') {
+			Some(eq) => eq,
+			None => break,
+		};
+		let key = rest[..eq].to_string();
+		rest = &rest[eq + 1..];
+
+		let value = if rest.starts_with('"') {
+			let end = rest[1..].find('"').map(|i| i + 1).unwrap_or(rest.len());
+			let value = rest[1..end].to_string();
+			rest = &rest[::std::cmp::min(end + 1, rest.len())..];
+			value
+		} else {
+			let end = rest.find(' ').unwrap_or(rest.len());
+			let value = rest[..end].to_string();
+			rest = &rest[end..];
+			value
+		};
+
+		attrs.insert(key, value);
+	}
+
+	(tag, attrs)
+}
+
+/// Reads a numeric attribute, or 0 if it is missing or malformed.
+fn attr_f64(attrs: &HashMap<String, String>, key: &str) -> f64 {
+	attrs.get(key).and_then(|value| value.parse().ok()).unwrap_or(0.0)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{attr_f64, parse_line};
+
+	#[test]
+	fn parse_line_reads_every_attribute() {
+		let (tag, attrs) = parse_line("char id=65   x=2 y=-3 xadvance=12");
+		assert_eq!(tag, "char");
+		assert_eq!(attrs.len(), 4);
+		assert_eq!(attrs["id"], "65");
+		assert!((attr_f64(&attrs, "y") + 3.0).abs() < 1e-9);
+		assert!(attr_f64(&attrs, "missing").abs() < 1e-9);
+	}
+
+	#[test]
+	fn parse_line_keeps_spaces_in_quoted_values() {
+		let (tag, attrs) = parse_line("page id=0 file=\"font page.png\" extra=1");
+		assert_eq!(tag, "page");
+		assert_eq!(attrs["file"], "font page.png");
+		assert_eq!(attrs["extra"], "1");
+	}
+
+	#[test]
+	fn parse_line_without_attributes() {
+		let (tag, attrs) = parse_line("  kernings  ");
+		assert_eq!(tag, "kernings");
+		assert!(attrs.is_empty());
+
+		//? An unterminated quote runs to the end of the line.
+		let (_, attrs) = parse_line("info face=\"Arial");
+		assert_eq!(attrs["face"], "Arial");
+	}
+}
@@ -0,0 +1,202 @@
+use ::std::fs;
+use ::std::path::Path;
+
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::pixels::PixelFormatEnum;
+use ::sdl2::render::{BlendMode, Renderer};
+use ::sdl2::surface::Surface;
+use ::sdl2_image::{LoadSurface, SaveSurface};
+
+/// The key which takes a screenshot.
+pub const SCREENSHOT_KEY: Keycode = Keycode::F12;
+
+/// Where screenshots are written.
+pub const SCREENSHOT_DIR: &'static str = "screenshots";
+
+/// The pixels of a rendered frame, as 32-bit ARGB.
+pub struct Frame {
+	pub w: u32,
+	pub h: u32,
+	pub pixels: Vec<u8>,
+}
+
+impl Frame {
+	/// Reads back what was rendered since the renderer was last cleared.
+	/// Must be called before the frame is presented.
+	pub fn read(renderer: &Renderer) -> Result<Frame, String> {
+		let (w, h) = try!(renderer.output_size().map_err(|e| e.to_string()));
+		let pixels = try!(renderer.read_pixels(None, PixelFormatEnum::ARGB8888).map_err(|e| e.to_string()));
+
+		Ok(Frame {
+			w: w,
+			h: h,
+			pixels: pixels,
+		})
+	}
+
+	/// Loads a PNG file written by `save_png`, or by any other program.
+	pub fn load_png(path: &Path) -> Result<Frame, String> {
+		let mut loaded: Surface = try!(LoadSurface::from_file(path).map_err(|e| e.to_string()));
+		let (w, h) = loaded.size();
+
+		//? The bindings cannot convert a surface to a format by name, so the
+		//? image is copied, as-is, onto a surface of the right format.
+		let mut surface = try!(Surface::new(w, h, PixelFormatEnum::ARGB8888).map_err(|e| e.to_string()));
+		try!(loaded.set_blend_mode(BlendMode::None).map_err(|e| e.to_string()));
+		try!(loaded.blit(None, &mut surface, None).map_err(|e| e.to_string()));
+		let pitch = surface.pitch();
+
+		//? Rows may be padded, so they are copied one by one.
+		let pixels = surface.with_lock(|data| {
+			let mut pixels = Vec::with_capacity((w * h * 4) as usize);
+			for y in 0..h {
+				let start = (y * pitch) as usize;
+				pixels.extend_from_slice(&data[start..start + (w * 4) as usize]);
+			}
+			pixels
+		});
+
+		Ok(Frame {
+			w: w,
+			h: h,
+			pixels: pixels,
+		})
+	}
+
+	/// The color of the pixel at (`x`, `y`), as (r, g, b, a).
+	pub fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8, u8) {
+		let i = ((y * self.w + x) * 4) as usize;
+		//? ARGB8888 is stored as a native-endian u32, hence BGRA in memory
+		//? on the little-endian machines we run on.
+		(self.pixels[i + 2], self.pixels[i + 1], self.pixels[i], self.pixels[i + 3])
+	}
+
+	pub fn set_pixel(&mut self, x: u32, y: u32, (r, g, b, a): (u8, u8, u8, u8)) {
+		let i = ((y * self.w + x) * 4) as usize;
+		self.pixels[i] = b;
+		self.pixels[i + 1] = g;
+		self.pixels[i + 2] = r;
+		self.pixels[i + 3] = a;
+	}
+
+	pub fn save_png(&mut self, path: &Path) -> Result<(), String> {
+		let (w, h) = (self.w, self.h);
+		let surface = try!(Surface::from_data(&mut self.pixels, w, h, w * 4, PixelFormatEnum::ARGB8888)
+			.map_err(|e| e.to_string()));
+		surface.save(path).map_err(|e| e.to_string())
+	}
+}
+
+/// Writes every `every`-th frame to a numbered file in `dir`.
+struct Sequence {
+	dir: String,
+	every: u32,
+	/// The frames seen since the capture started.
+	frame: u32,
+	/// The number of the next file.
+	index: u32,
+}
+
+/// Captures what the game renders: single screenshots, or a sequence of
+/// frames, e.g. to make a trailer. Frames are captured after the view is
+/// rendered, but before the debug overlays are drawn over it.
+pub struct Capture {
+	screenshot_requested: bool,
+	sequence: Option<Sequence>,
+}
+
+impl Capture {
+	pub fn new() -> Capture {
+		Capture {
+			screenshot_requested: false,
+			sequence: None,
+		}
+	}
+
+	/// Takes a screenshot of the next frame, written to `SCREENSHOT_DIR`
+	/// with the time at which it was taken as its name.
+	pub fn request_screenshot(&mut self) {
+		self.screenshot_requested = true;
+	}
+
+	/// Writes every `every`-th frame, starting with the next one, to `dir`
+	/// as `frame_00000.png`, `frame_00001.png`...
+	pub fn start_sequence(&mut self, dir: &str, every: u32) -> Result<(), String> {
+		try!(fs::create_dir_all(dir).map_err(|e| e.to_string()));
+		self.sequence = Some(Sequence {
+			dir: dir.to_string(),
+			every: ::std::cmp::max(every, 1),
+			frame: 0,
+			index: 0,
+		});
+		Ok(())
+	}
+
+	pub fn stop_sequence(&mut self) {
+		self.sequence = None;
+	}
+
+	pub fn is_capturing(&self) -> bool {
+		self.sequence.is_some()
+	}
+
+	/// Writes the frame which was just rendered where it was requested, if
+	/// anywhere. Called by `spawn` after the view is rendered.
+	pub fn capture(&mut self, renderer: &Renderer, screenshot_key: bool) {
+		let screenshot = self.screenshot_requested || screenshot_key;
+		self.screenshot_requested = false;
+
+		let sequence_path = match self.sequence {
+			Some(ref mut sequence) => {
+				let due = sequence.frame % sequence.every == 0;
+				sequence.frame += 1;
+				if due {
+					sequence.index += 1;
+					Some(Path::new(&sequence.dir).join(format!("frame_{:05}.png", sequence.index - 1)))
+				} else {
+					None
+				}
+			},
+			None => None,
+		};
+
+		if !screenshot && sequence_path.is_none() {
+			return;
+		}
+
+		let mut frame = match Frame::read(renderer) {
+			Ok(frame) => frame,
+			Err(e) => {
+				println!("Could not read the frame back: {}", e);
+				return;
+			},
+		};
+
+		if let Some(path) = sequence_path {
+			if let Err(e) = frame.save_png(&path) {
+				println!("Could not write {}: {}", path.display(), e);
+				self.sequence = None;
+			}
+		}
+
+		if screenshot {
+			match write_screenshot(&mut frame) {
+				Ok(path) => println!("Saved a screenshot to {}", path),
+				Err(e) => println!("Could not save the screenshot: {}", e),
+			}
+		}
+	}
+}
+
+fn write_screenshot(frame: &mut Frame) -> Result<String, String> {
+	try!(fs::create_dir_all(SCREENSHOT_DIR).map_err(|e| e.to_string()));
+
+	let now = ::std::time::SystemTime::now()
+		.duration_since(::std::time::UNIX_EPOCH)
+		.map(|d| (d.as_secs(), d.subsec_nanos() / 1_000_000))
+		.unwrap_or((0, 0));
+	let path = Path::new(SCREENSHOT_DIR).join(format!("screenshot-{}-{:03}.png", now.0, now.1));
+
+	try!(frame.save_png(&path));
+	Ok(path.display().to_string())
+}
@@ -0,0 +1,274 @@
+use ::std::collections::{HashMap, VecDeque};
+
+use ::sdl2::TimerSubsystem;
+
+/// How the game waits for the next frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pacing {
+	/// The renderer waits for the screen when the frame is presented. The
+	/// clock only caps frames slightly above the target rate, in case the
+	/// driver ignores vsync, as it often does in a window.
+	VSync,
+	/// Sleeps for most of the wait, then spins until the next frame is due.
+	/// Sleeping alone is only accurate to a millisecond or two.
+	Hybrid,
+	/// Renders as many frames as possible, to measure how long they take.
+	Uncapped,
+}
+
+impl Pacing {
+	pub fn name(self) -> &'static str {
+		match self {
+			Pacing::VSync => "vsync",
+			Pacing::Hybrid => "hybrid",
+			Pacing::Uncapped => "uncapped",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Pacing> {
+		match name {
+			"vsync" => Some(Pacing::VSync),
+			"hybrid" => Some(Pacing::Hybrid),
+			"uncapped" => Some(Pacing::Uncapped),
+			_ => None,
+		}
+	}
+
+	/// The most frames per second the clock lets through when aiming for
+	/// `target_fps`, or `None` if it does not wait.
+	pub fn max_fps(self, target_fps: f64) -> Option<f64> {
+		match self {
+			_ if target_fps <= 0.0 => None,
+			//? The headroom keeps the clock from delaying a frame which the
+			//? screen already paced, which would make it miss a refresh.
+			Pacing::VSync => Some(target_fps * VSYNC_HEADROOM),
+			Pacing::Hybrid => Some(target_fps),
+			Pacing::Uncapped => None,
+		}
+	}
+}
+
+/// How much faster than the target rate `Pacing::VSync` lets frames go.
+const VSYNC_HEADROOM: f64 = 1.05;
+
+/// The parts of the game whose time can be scaled separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Layer {
+	/// What the player plays with: the ship, and later enemies and bullets.
+	Gameplay,
+	/// The scenery, such as the scrolling backgrounds.
+	Background,
+	/// Menus and their animations, which keep running while paused.
+	Ui,
+}
+
+impl Layer {
+	pub fn name(self) -> &'static str {
+		match self {
+			Layer::Gameplay => "gameplay",
+			Layer::Background => "background",
+			Layer::Ui => "ui",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Layer> {
+		match name {
+			"gameplay" => Some(Layer::Gameplay),
+			"background" => Some(Layer::Background),
+			"ui" => Some(Layer::Ui),
+			_ => None,
+		}
+	}
+}
+
+/// Whether gameplay was paused under each view which another one was
+/// pushed on, so that popping back to a view restores its pause: the view
+/// underneath must not stay frozen by the one which was popped, nor be
+/// unpaused behind its own pause menu.
+pub struct PauseStack {
+	below: Vec<bool>,
+}
+
+impl PauseStack {
+	pub fn new() -> PauseStack {
+		PauseStack {
+			below: Vec::new(),
+		}
+	}
+
+	/// Called when a view is pushed on top of one which left the clock
+	/// `paused`.
+	pub fn push(&mut self, paused: bool) {
+		self.below.push(paused);
+	}
+
+	/// Called when the top view is popped. Returns whether the view under
+	/// it had paused the clock.
+	pub fn pop(&mut self) -> bool {
+		self.below.pop().unwrap_or(false)
+	}
+}
+
+/// How long before the next frame the clock stops sleeping and spins, in
+/// seconds.
+const SPIN_MARGIN: f64 = 0.002;
+
+/// Measures the duration of frames with the performance counter, and paces
+/// them.
+///
+/// It also decides how much time passes for each `Layer` during a frame:
+/// views move things by `delta(layer)` rather than by the `elapsed` they
+/// are given, so that gameplay can be slowed down, frozen for a hitstop or
+/// paused while the menus keep animating.
+pub struct FrameClock {
+	timer: TimerSubsystem,
+	pub pacing: Pacing,
+	/// The frame rate aimed for by `Pacing::Hybrid`, which also caps
+	/// `Pacing::VSync`.
+	pub target_fps: f64,
+	/// The longest a frame may last, in seconds. After a hitch, such as the
+	/// window being dragged, the game slows down instead of skipping ahead,
+	/// so that fast entities do not go through walls.
+	pub max_elapsed: f64,
+	/// How many frames are averaged to smooth the jitter of their duration;
+	/// 1 turns smoothing off.
+	pub smoothing: usize,
+	/// Scales the time of every layer, e.g. 0.5 for slow motion.
+	pub scale: f64,
+	/// Stops every layer but `Layer::Ui`. Reset when the view changes.
+	pub paused: bool,
+	layer_scales: HashMap<Layer, f64>,
+	/// For how long, in seconds, every layer but `Layer::Ui` stays frozen.
+	hitstop: f64,
+	/// The duration of the current frame, before scaling.
+	elapsed: f64,
+	frequency: f64,
+	start: u64,
+	last: u64,
+	/// The duration of the last frames, capped, most recent last.
+	recent: VecDeque<f64>,
+}
+
+impl FrameClock {
+	pub fn new(timer: TimerSubsystem, pacing: Pacing) -> FrameClock {
+		let now = timer.performance_counter();
+		let frequency = timer.performance_frequency() as f64;
+
+		FrameClock {
+			timer: timer,
+			pacing: pacing,
+			target_fps: 60.0,
+			max_elapsed: 0.1,
+			smoothing: 4,
+			scale: 1.0,
+			paused: false,
+			layer_scales: HashMap::new(),
+			hitstop: 0.0,
+			elapsed: 0.0,
+			frequency: frequency,
+			start: now,
+			last: now,
+			recent: VecDeque::new(),
+		}
+	}
+
+	/// The time since the clock was created, in seconds.
+	pub fn now(&self) -> f64 {
+		(self.timer.performance_counter() - self.start) as f64 / self.frequency
+	}
+
+	/// Waits until the next frame is due, as decided by `pacing`, and
+	/// returns the time it should simulate, in seconds. Called by `spawn`
+	/// at the start of every frame.
+	pub fn wait_next_frame(&mut self) -> f64 {
+		if let Some(max_fps) = self.pacing.max_fps(self.target_fps) {
+			let due = self.last + (self.frequency / max_fps) as u64;
+			loop {
+				let now = self.timer.performance_counter();
+				if now >= due {
+					break;
+				}
+				let remaining = (due - now) as f64 / self.frequency;
+				if remaining > SPIN_MARGIN {
+					self.timer.delay(((remaining - SPIN_MARGIN) * 1000.0) as u32);
+				}
+			}
+		}
+
+		let now = self.timer.performance_counter();
+		let elapsed = (now - self.last) as f64 / self.frequency;
+		self.last = now;
+
+		self.recent.push_back(elapsed.min(self.max_elapsed));
+		while self.recent.len() > ::std::cmp::max(self.smoothing, 1) {
+			self.recent.pop_front();
+		}
+		self.recent.iter().fold(0.0, |total, t| total + t) / self.recent.len() as f64
+	}
+
+	/// Starts the frame which lasts `elapsed` seconds, before scaling.
+	/// Called by `Phi` once the duration of the frame is known.
+	pub fn advance(&mut self, elapsed: f64) {
+		self.elapsed = elapsed;
+		self.hitstop = (self.hitstop - elapsed).max(0.0);
+	}
+
+	/// The duration of the current frame, before scaling.
+	pub fn elapsed(&self) -> f64 {
+		self.elapsed
+	}
+
+	/// How much time passes for `layer` during the current frame.
+	pub fn delta(&self, layer: Layer) -> f64 {
+		if layer != Layer::Ui && (self.paused || self.hitstop > 0.0) {
+			0.0
+		} else {
+			self.elapsed * self.scale * self.layer_scale(layer)
+		}
+	}
+
+	pub fn layer_scale(&self, layer: Layer) -> f64 {
+		self.layer_scales.get(&layer).cloned().unwrap_or(1.0)
+	}
+
+	pub fn set_layer_scale(&mut self, layer: Layer, scale: f64) {
+		self.layer_scales.insert(layer, scale);
+	}
+
+	/// Freezes everything but the UI for `duration` seconds, to give weight
+	/// to a big hit. Hitstops do not add up: the longest one wins.
+	pub fn hitstop(&mut self, duration: f64) {
+		self.hitstop = self.hitstop.max(duration);
+	}
+
+	pub fn in_hitstop(&self) -> bool {
+		self.hitstop > 0.0
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{Pacing, PauseStack};
+
+	#[test]
+	fn vsync_is_capped_slightly_above_the_target() {
+		let cap = Pacing::VSync.max_fps(60.0).unwrap();
+		assert!(cap > 60.0 && cap < 66.0);
+		assert!(Pacing::Hybrid.max_fps(60.0).map_or(false, |fps| (fps - 60.0).abs() < 1e-9));
+		assert!(Pacing::Uncapped.max_fps(60.0).is_none());
+		//? A target of 0 turns the cap off.
+		assert!(Pacing::VSync.max_fps(0.0).is_none());
+	}
+	#[test]
+	fn popping_restores_the_pause_of_the_view_below() {
+		let mut pauses = PauseStack::new();
+		//? A running game opens the options, which open the controls.
+		pauses.push(false);
+		pauses.push(true);
+		assert!(pauses.pop());
+		assert!(!pauses.pop());
+		//? Popping the last view leaves nothing paused.
+		assert!(!pauses.pop());
+	}
+}
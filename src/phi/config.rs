@@ -0,0 +1,281 @@
+use ::std::fs::File;
+use ::std::io::{self, Read, Write};
+
+/// Where the settings chosen by the player are stored.
+pub const CONFIG_PATH: &'static str = "settings.cfg";
+
+/// The version of the settings file. Files written by another version are
+/// ignored rather than misread.
+const VERSION: u32 = 1;
+
+/// The step by which volumes change. Volumes are always a multiple of it.
+pub const VOLUME_STEP: f64 = 0.1;
+
+/// The window sizes offered to the player.
+pub const RESOLUTIONS: [(u32, u32); 4] = [
+	(800, 600),
+	(1024, 768),
+	(1280, 720),
+	(1920, 1080),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+	Easy,
+	Normal,
+	Hard,
+}
+
+/// Every difficulty, from the easiest to the hardest.
+pub const DIFFICULTIES: [Difficulty; 3] = [
+	Difficulty::Easy,
+	Difficulty::Normal,
+	Difficulty::Hard,
+];
+
+impl Difficulty {
+	/// The identifier of the difficulty in the settings file.
+	pub fn name(self) -> &'static str {
+		match self {
+			Difficulty::Easy => "easy",
+			Difficulty::Normal => "normal",
+			Difficulty::Hard => "hard",
+		}
+	}
+
+	/// The name of the difficulty, as shown to the player.
+	pub fn label(self) -> &'static str {
+		match self {
+			Difficulty::Easy => "Easy",
+			Difficulty::Normal => "Normal",
+			Difficulty::Hard => "Hard",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Difficulty> {
+		DIFFICULTIES.iter().cloned().find(|d| d.name() == name)
+	}
+}
+
+/// The settings of the game. The bindings are stored separately, by
+/// `InputMap`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+	/// Between 0 and 1.
+	pub music_volume: f64,
+	/// Between 0 and 1.
+	pub effects_volume: f64,
+	pub fullscreen: bool,
+	/// The size of the window, when it is not fullscreen; one of `RESOLUTIONS`.
+	pub resolution: (u32, u32),
+	/// Only applied when the game starts, as the renderer must be rebuilt.
+	pub vsync: bool,
+	pub difficulty: Difficulty,
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			music_volume: 0.8,
+			effects_volume: 0.8,
+			fullscreen: false,
+			resolution: (800, 600),
+			vsync: true,
+			difficulty: Difficulty::Normal,
+		}
+	}
+}
+
+impl Config {
+	/// Loads the settings stored at `path`. Settings which are missing or
+	/// cannot be parsed keep their default value, and so does everything if
+	/// the file cannot be read or was written by another version.
+	///
+	/// The file starts with its version, followed by one setting per line:
+	///
+	/// ```text
+	/// version = 1
+	/// fullscreen = false
+	/// resolution = 800x600
+	/// ```
+	pub fn load(path: &str) -> Config {
+		let mut config = Config::default();
+
+		let mut source = String::new();
+		if File::open(path).and_then(|mut file| file.read_to_string(&mut source)).is_err() {
+			return config;
+		}
+
+		let mut lines = source.lines().filter_map(|line| {
+			let mut parts = line.splitn(2, '=');
+			match (parts.next(), parts.next()) {
+				(Some(name), Some(value)) => Some((name.trim(), value.trim())),
+				_ => None,
+			}
+		});
+
+		match lines.next() {
+			Some(("version", version)) if version == VERSION.to_string() => {},
+			_ => {
+				println!("Ignoring {}, which was written by another version", path);
+				return config;
+			},
+		}
+
+		for (name, value) in lines {
+			match name {
+				"music_volume" => parse_volume(value, &mut config.music_volume),
+				"effects_volume" => parse_volume(value, &mut config.effects_volume),
+				"fullscreen" => parse_bool(value, &mut config.fullscreen),
+				"vsync" => parse_bool(value, &mut config.vsync),
+				"resolution" => {
+					let mut parts = value.splitn(2, 'x');
+					let w = parts.next().and_then(|w| w.parse().ok());
+					let h = parts.next().and_then(|h| h.parse().ok());
+					//? Only the sizes offered by the options menu are accepted:
+					//? the window could not be built at any size.
+					if let (Some(w), Some(h)) = (w, h) {
+						if RESOLUTIONS.contains(&(w, h)) {
+							config.resolution = (w, h);
+						}
+					}
+				},
+				"difficulty" => {
+					if let Some(difficulty) = Difficulty::from_name(value) {
+						config.difficulty = difficulty;
+					}
+				},
+				_ => {},
+			}
+		}
+
+		config
+	}
+
+	pub fn save(&self, path: &str) -> io::Result<()> {
+		let mut file = try!(File::create(path));
+
+		try!(writeln!(file, "version = {}", VERSION));
+		try!(writeln!(file, "music_volume = {}", self.music_volume));
+		try!(writeln!(file, "effects_volume = {}", self.effects_volume));
+		try!(writeln!(file, "fullscreen = {}", self.fullscreen));
+		try!(writeln!(file, "resolution = {}x{}", self.resolution.0, self.resolution.1));
+		try!(writeln!(file, "vsync = {}", self.vsync));
+		try!(writeln!(file, "difficulty = {}", self.difficulty.name()));
+
+		Ok(())
+	}
+}
+
+fn parse_volume(value: &str, volume: &mut f64) {
+	if let Ok(value) = value.parse::<f64>() {
+		if value.is_finite() {
+			//? Snapped to the step, so that a hand-edited file cannot hold a
+			//? value which the options menu would never produce.
+			*volume = ((value / VOLUME_STEP).round() * VOLUME_STEP).max(0.0).min(1.0);
+		}
+	}
+}
+
+fn parse_bool(value: &str, flag: &mut bool) {
+	if let Ok(value) = value.parse() {
+		*flag = value;
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_volume, Config, Difficulty};
+
+	use ::std::fs::File;
+	use ::std::io::Write;
+
+	fn close(a: f64, b: f64) -> bool {
+		(a - b).abs() < 1e-9
+	}
+
+	/// Writes `contents` to a file of the temporary directory and returns
+	/// its path.
+	fn temp_file(name: &str, contents: &str) -> String {
+		let path = ::std::env::temp_dir().join(name);
+		File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+		path.to_string_lossy().into_owned()
+	}
+
+	#[test]
+	fn parse_volume_snaps_and_clamps() {
+		let mut volume = 0.5;
+		parse_volume("0.46", &mut volume);
+		assert!(close(volume, 0.5));
+		parse_volume("0.73", &mut volume);
+		assert!(close(volume, 0.7));
+		parse_volume("3", &mut volume);
+		assert!(close(volume, 1.0));
+		parse_volume("-1", &mut volume);
+		assert!(close(volume, 0.0));
+	}
+
+	#[test]
+	fn parse_volume_ignores_invalid_values() {
+		let mut volume = 0.5;
+		parse_volume("loud", &mut volume);
+		parse_volume("NaN", &mut volume);
+		parse_volume("inf", &mut volume);
+		assert!(close(volume, 0.5));
+	}
+
+	#[test]
+	fn load_keeps_the_defaults_of_missing_and_invalid_settings() {
+		let path = temp_file("arcade-rs-test-settings.cfg",
+			"version = 1\nmusic_volume = 0.3\nfullscreen = yes\nresolution = 0x600\ndifficulty = hard\n");
+		let config = Config::load(&path);
+		let defaults = Config::default();
+
+		assert!(close(config.music_volume, 0.3));
+		assert!(close(config.effects_volume, defaults.effects_volume));
+		assert_eq!(config.fullscreen, defaults.fullscreen);
+		assert_eq!(config.resolution, defaults.resolution);
+		assert_eq!(config.difficulty, Difficulty::Hard);
+	}
+
+	#[test]
+	fn load_only_accepts_the_resolutions_of_the_menu() {
+		let path = temp_file("arcade-rs-test-settings-huge.cfg", "version = 1\nresolution = 99999x99999\n");
+		assert_eq!(Config::load(&path).resolution, Config::default().resolution);
+
+		let path = temp_file("arcade-rs-test-settings-odd.cfg", "version = 1\nresolution = 1000x700\n");
+		assert_eq!(Config::load(&path).resolution, Config::default().resolution);
+
+		let path = temp_file("arcade-rs-test-settings-hd.cfg", "version = 1\nresolution = 1920x1080\n");
+		assert_eq!(Config::load(&path).resolution, (1920, 1080));
+	}
+
+	#[test]
+	fn load_ignores_files_of_another_version() {
+		let path = temp_file("arcade-rs-test-settings-v0.cfg", "version = 0\ndifficulty = hard\n");
+		assert_eq!(Config::load(&path).difficulty, Config::default().difficulty);
+	}
+
+	#[test]
+	fn save_then_load() {
+		let path = ::std::env::temp_dir().join("arcade-rs-test-settings-saved.cfg");
+		let path = path.to_string_lossy().into_owned();
+		let config = Config {
+			music_volume: 0.2,
+			fullscreen: true,
+			resolution: (1280, 720),
+			vsync: false,
+			difficulty: Difficulty::Easy,
+			.. Config::default()
+		};
+		config.save(&path).unwrap();
+
+		let loaded = Config::load(&path);
+		assert!(close(loaded.music_volume, 0.2));
+		assert_eq!(loaded.fullscreen, true);
+		assert_eq!(loaded.resolution, (1280, 720));
+		assert_eq!(loaded.vsync, false);
+		assert_eq!(loaded.difficulty, Difficulty::Easy);
+	}
+}
@@ -0,0 +1,511 @@
+use ::phi::{ImmediateEvents, Phi};
+use ::phi::data::Rectangle;
+use ::phi::events::EditKey;
+use ::phi::text::{Align, CopyText, TextOptions};
+
+use ::std::collections::BTreeMap;
+use ::std::rc::Rc;
+
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::pixels::Color;
+use ::sdl2::render::BlendMode;
+
+/// The key which opens and closes the console.
+pub const TOGGLE_KEY: Keycode = Keycode::Backquote;
+
+const FONT: &'static str = "assets/belligerent.ttf";
+const FONT_SIZE: i32 = 16;
+const LINE_H: f64 = 18.0;
+/// How many lines of output are kept.
+const SCROLLBACK: usize = 200;
+/// How many commands are kept in the history.
+const HISTORY: usize = 50;
+
+/// What a command does with the console's arguments. The text it returns,
+/// or the error, is printed to the console.
+pub type Handler = Rc<Fn(&mut Phi, &[&str]) -> Result<String, String>>;
+
+struct Command {
+	help: String,
+	/// `None` for the commands which are handled by a view: they are queued
+	/// in `Console::invocations` instead.
+	handler: Option<Handler>,
+}
+
+/// A command which was typed in the console, for the view which handles it.
+#[derive(Clone, Debug)]
+pub struct Invocation {
+	pub name: String,
+	pub args: Vec<String>,
+}
+
+/// A console which drops down from the top of the window, in which the
+/// developer types commands such as `timescale 0.5`.
+///
+/// Subsystems `register` the commands they handle directly. Views, which
+/// cannot be reached from a handler, `register_queued` theirs, and read
+/// them from `invocations` during the frame in which they were typed.
+pub struct Console {
+	pub open: bool,
+	commands: BTreeMap<String, Command>,
+	input: String,
+	output: Vec<String>,
+	/// How many lines of output are scrolled back from the last one.
+	scroll: usize,
+	history: Vec<String>,
+	/// The entry of the history being edited, when browsing it.
+	history_pos: Option<usize>,
+	/// Whether text input was active before the console was opened, so that
+	/// it can be restored.
+	text_input_was_active: bool,
+	/// The commands of views typed during this frame.
+	pub invocations: Vec<Invocation>,
+}
+
+impl Console {
+	pub fn new() -> Console {
+		let mut console = Console {
+			open: false,
+			commands: BTreeMap::new(),
+			input: String::new(),
+			output: Vec::new(),
+			scroll: 0,
+			history: Vec::new(),
+			history_pos: None,
+			text_input_was_active: false,
+			invocations: Vec::new(),
+		};
+
+		console.register("help", "help: lists the commands", Rc::new(|phi: &mut Phi, _: &[&str]| {
+			let lines: Vec<String> = phi.console.commands.values()
+				.map(|command| command.help.clone())
+				.collect();
+			Ok(lines.join("\n"))
+		}));
+		console.register("clear", "clear: empties the console", Rc::new(|phi: &mut Phi, _: &[&str]| {
+			phi.console.output.clear();
+			phi.console.scroll = 0;
+			Ok(String::new())
+		}));
+		console.register("debug", "debug: shows or hides the debug overlay", Rc::new(|phi: &mut Phi, _: &[&str]| {
+			phi.debug.visible = !phi.debug.visible;
+			Ok(String::new())
+		}));
+		console.register("shapes", "shapes: shows or hides the debug shapes", Rc::new(|phi: &mut Phi, _: &[&str]| {
+			phi.debug_draw.enabled = !phi.debug_draw.enabled;
+			Ok(String::new())
+		}));
+		console.register("record", "record <file>: records the input to a replay", Rc::new(|phi: &mut Phi, args: &[&str]| {
+			match args.first() {
+				Some(path) => phi.start_recording(path)
+					.map(|_| format!("Recording to {}", path))
+					.map_err(|e| e.to_string()),
+				None => Err("Usage: record <file>".to_string()),
+			}
+		}));
+		console.register("screenshot", "screenshot: saves the next frame to a PNG file", Rc::new(|phi: &mut Phi, _: &[&str]| {
+			phi.capture.request_screenshot();
+			Ok(String::new())
+		}));
+		console.register("capture", "capture <dir> [every] | capture stop: saves every nth frame to <dir>", Rc::new(|phi: &mut Phi, args: &[&str]| {
+			match args.first() {
+				Some(&"stop") => {
+					phi.capture.stop_sequence();
+					Ok("Stopped capturing".to_string())
+				},
+				Some(dir) => {
+					let every = args.get(1).and_then(|every| every.parse().ok()).unwrap_or(1);
+					phi.capture.start_sequence(dir, every)
+						.map(|_| format!("Capturing every {} frames to {}", every, dir))
+				},
+				None => Err("Usage: capture <dir> [every] | capture stop".to_string()),
+			}
+		}));
+		console.register("profile", "profile: shows or hides the frame profiler", Rc::new(|phi: &mut Phi, _: &[&str]| {
+			phi.profiler.visible = !phi.profiler.visible;
+			Ok(String::new())
+		}));
+		console.register("trace", "trace <file> [frames]: writes the next frames as a Chrome trace", Rc::new(|phi: &mut Phi, args: &[&str]| {
+			match args.first() {
+				Some(path) => {
+					let frames = args.get(1).and_then(|frames| frames.parse().ok()).unwrap_or(300);
+					phi.profiler.start_trace(path, frames);
+					Ok(format!("Tracing {} frames to {}", frames, path))
+				},
+				None => Err("Usage: trace <file> [frames]".to_string()),
+			}
+		}));
+		console.register("pacing", "pacing [vsync|hybrid|uncapped]: shows or changes how frames are paced", Rc::new(|phi: &mut Phi, args: &[&str]| {
+			match args.first() {
+				Some(name) => match ::phi::clock::Pacing::from_name(name) {
+					Some(pacing) => {
+						phi.clock.pacing = pacing;
+						if pacing == ::phi::clock::Pacing::VSync && !phi.config.vsync {
+							Ok("VSync is off until the game restarts, so only the clock caps frames".to_string())
+						} else {
+							Ok(String::new())
+						}
+					},
+					None => Err(format!("Unknown pacing: {}", name)),
+				},
+				None => Ok(format!("Pacing: {} ({} fps target, {:.0} ms cap, {} frames smoothed)",
+					phi.clock.pacing.name(), phi.clock.target_fps,
+					phi.clock.max_elapsed * 1000.0, phi.clock.smoothing)),
+			}
+		}));
+		console.register("timescale", "timescale [scale] [layer]: shows or changes how fast time passes, for every layer or one", Rc::new(|phi: &mut Phi, args: &[&str]| {
+			use ::phi::clock::Layer;
+
+			let scale = match args.first() {
+				Some(scale) => try!(scale.parse::<f64>().map_err(|_| format!("Not a number: {}", scale))),
+				None => {
+					let layers: Vec<String> = [Layer::Gameplay, Layer::Background, Layer::Ui].iter()
+						.map(|&layer| format!("{} {}", layer.name(), phi.clock.layer_scale(layer)))
+						.collect();
+					return Ok(format!("Time scale: {} ({})", phi.clock.scale, layers.join(", ")));
+				},
+			};
+
+			match args.get(1) {
+				Some(name) => match Layer::from_name(name) {
+					Some(layer) => phi.clock.set_layer_scale(layer, scale),
+					None => return Err(format!("Unknown layer: {}", name)),
+				},
+				None => phi.clock.scale = scale,
+			}
+			Ok(String::new())
+		}));
+		console.register("hitstop", "hitstop <seconds>: freezes everything but the menus", Rc::new(|phi: &mut Phi, args: &[&str]| {
+			match args.first().and_then(|arg| arg.parse().ok()) {
+				Some(duration) => {
+					phi.clock.hitstop(duration);
+					Ok(String::new())
+				},
+				None => Err("Usage: hitstop <seconds>".to_string()),
+			}
+		}));
+		console.register("after", "after <seconds> <command>: runs a command later, e.g. `after 2 screenshot`", Rc::new(|phi: &mut Phi, args: &[&str]| {
+			let delay = args.first().and_then(|arg| arg.parse().ok());
+			match delay {
+				Some(delay) if args.len() > 1 => {
+					let line = args[1..].join(" ");
+					phi.scheduler.after(::phi::clock::Layer::Ui, delay, Box::new(move |phi: &mut Phi| execute(phi, &line)));
+					Ok(String::new())
+				},
+				_ => Err("Usage: after <seconds> <command>".to_string()),
+			}
+		}));
+		console.register("stop_record", "stop_record: stops recording the input", Rc::new(|phi: &mut Phi, _: &[&str]| {
+			phi.stop_recording();
+			Ok(String::new())
+		}));
+
+		console
+	}
+
+	/// Adds a command, or replaces the one with the same name. `help` is
+	/// shown by the `help` command, and should start with the usage.
+	pub fn register(&mut self, name: &str, help: &str, handler: Handler) {
+		self.commands.insert(name.to_string(), Command {
+			help: help.to_string(),
+			handler: Some(handler),
+		});
+	}
+
+	/// Adds a command which is handled by a view, through `invocations`.
+	pub fn register_queued(&mut self, name: &str, help: &str) {
+		self.commands.insert(name.to_string(), Command {
+			help: help.to_string(),
+			handler: None,
+		});
+	}
+
+	/// Removes a command, e.g. one of a view which is being left.
+	pub fn unregister(&mut self, name: &str) {
+		self.commands.remove(name);
+	}
+
+	/// Writes `text` to the console, one line per line of text.
+	pub fn print(&mut self, text: &str) {
+		for line in text.lines() {
+			self.output.push(line.to_string());
+		}
+		if self.output.len() > SCROLLBACK {
+			let extra = self.output.len() - SCROLLBACK;
+			self.output.drain(..extra);
+		}
+		self.scroll = 0;
+	}
+
+	/// Completes the name of the command being typed: fully if a single
+	/// command starts with it, otherwise as far as they all agree, listing
+	/// them.
+	fn complete(&mut self) {
+		if self.input.contains(' ') {
+			return;
+		}
+
+		let candidates: Vec<String> = self.commands.keys()
+			.filter(|name| name.starts_with(&self.input[..]))
+			.cloned()
+			.collect();
+
+		match candidates.len() {
+			0 => {},
+			1 => self.input = format!("{} ", candidates[0]),
+			_ => {
+				let first = &candidates[0];
+				let common = (0..first.len() + 1).rev()
+					.filter(|&len| first.is_char_boundary(len))
+					.find(|&len| candidates.iter().all(|c| c.starts_with(&first[..len])))
+					.unwrap_or(0);
+				self.input = first[..common].to_string();
+				let list = candidates.join("  ");
+				self.print(&list);
+			},
+		}
+	}
+
+	/// Handles what was typed in the open console during this frame, and
+	/// returns whether a line was submitted. The text, edit keys and keys
+	/// are taken out of `now`: the console owns the keyboard while it is
+	/// open, so keys such as F3 do not toggle the overlays behind it, and do
+	/// not reach the views which read keys directly.
+	fn take_input(&mut self, now: &mut ImmediateEvents) -> bool {
+		let text = ::std::mem::replace(&mut now.text, String::new());
+		self.input.push_str(&text);
+
+		let mut submitted = false;
+		for key in ::std::mem::replace(&mut now.edit_keys, Vec::new()) {
+			match key {
+				EditKey::Backspace => { self.input.pop(); },
+				EditKey::Submit => submitted = true,
+				_ => {},
+			}
+		}
+
+		for (key, pressed) in ::std::mem::replace(&mut now.keys, Vec::new()) {
+			if !pressed {
+				continue;
+			}
+			match key {
+				Keycode::Tab => self.complete(),
+				Keycode::Up => self.browse_history(-1),
+				Keycode::Down => self.browse_history(1),
+				Keycode::PageUp => self.scroll = ::std::cmp::min(self.scroll + 5, self.output.len()),
+				Keycode::PageDown => self.scroll = self.scroll.saturating_sub(5),
+				_ => {},
+			}
+		}
+
+		submitted
+	}
+
+	/// Moves through the history; `dir` is -1 for older and 1 for newer.
+	fn browse_history(&mut self, dir: i32) {
+		if self.history.is_empty() {
+			return;
+		}
+
+		let pos = match (self.history_pos, dir) {
+			(None, -1) => Some(self.history.len() - 1),
+			(None, _) => None,
+			(Some(0), -1) => Some(0),
+			(Some(pos), -1) => Some(pos - 1),
+			(Some(pos), _) if pos + 1 < self.history.len() => Some(pos + 1),
+			(Some(_), _) => None,
+		};
+
+		self.history_pos = pos;
+		self.input = pos.map(|pos| self.history[pos].clone()).unwrap_or(String::new());
+	}
+}
+
+/// Opens or closes the console, and handles what is typed in it. Called by
+/// `spawn` before the view is rendered. While the console is open, the
+/// actions of `phi.input` are neither triggered nor held, and the keys of
+/// `phi.events.now` are taken out; only the key which closes it is left.
+pub fn update(phi: &mut Phi) {
+	phi.console.invocations.clear();
+
+	if phi.events.now.keys.contains(&(TOGGLE_KEY, true)) {
+		let open = !phi.console.open;
+		phi.console.open = open;
+		phi.input.set_suspended(open);
+
+		if open {
+			phi.console.text_input_was_active = phi.events.is_text_input_active();
+			phi.events.start_text_input();
+		} else if !phi.console.text_input_was_active {
+			phi.events.stop_text_input();
+		}
+		//? The key which opened the console also typed its character, which
+		//? should not reach the view; nor should keys typed along with it.
+		phi.events.now.text.clear();
+		phi.events.now.keys.retain(|&(key, _)| key == TOGGLE_KEY);
+		return;
+	}
+
+	if !phi.console.open {
+		return;
+	}
+
+	phi.input.clear_frame();
+
+	let submitted = phi.console.take_input(&mut phi.events.now);
+	if submitted {
+		let line = ::std::mem::replace(&mut phi.console.input, String::new());
+		execute(phi, &line);
+	}
+}
+
+/// Runs a line of the console, as if it was typed.
+pub fn execute(phi: &mut Phi, line: &str) {
+	let line = line.trim();
+	if line.is_empty() {
+		return;
+	}
+
+	{
+		let console = &mut phi.console;
+		console.print(&format!("> {}", line));
+		if console.history.last().map(|last| &last[..]) != Some(line) {
+			console.history.push(line.to_string());
+			if console.history.len() > HISTORY {
+				console.history.remove(0);
+			}
+		}
+		console.history_pos = None;
+	}
+
+	let mut words = line.split_whitespace();
+	let name = words.next().unwrap();
+	let args: Vec<&str> = words.collect();
+
+	//? The handler is cloned out of the registry, so that it can be given
+	//? `phi`, console included.
+	let handler = phi.console.commands.get(name).map(|command| command.handler.clone());
+
+	match handler {
+		Some(Some(handler)) => match handler(phi, &args) {
+			Ok(output) => phi.console.print(&output),
+			Err(error) => phi.console.print(&format!("Error: {}", error)),
+		},
+		Some(None) => phi.console.invocations.push(Invocation {
+			name: name.to_string(),
+			args: args.iter().map(|arg| arg.to_string()).collect(),
+		}),
+		None => phi.console.print(&format!("Unknown command: {} (try `help`)", name)),
+	}
+}
+
+/// Draws the console over the top half of the window, if it is open.
+pub fn render(phi: &mut Phi) {
+	if !phi.console.open {
+		return;
+	}
+
+	let (win_w, win_h) = phi.output_size();
+	let (win_w, height) = (win_w as f64, (win_h / 2) as f64);
+
+	phi.renderer.set_blend_mode(BlendMode::Blend);
+	phi.renderer.set_draw_color(Color::RGBA(20,5,20,220));
+	phi.renderer.fill_rect(Rectangle {
+		x: 0.0,
+		y: 0.0,
+		w: win_w,
+		h: height,
+	}.to_sdl().unwrap());
+	phi.renderer.set_blend_mode(BlendMode::None);
+	phi.renderer.set_draw_color(Color::RGB(140,30,140));
+	phi.renderer.fill_rect(Rectangle {
+		x: 0.0,
+		y: height,
+		w: win_w,
+		h: 2.0,
+	}.to_sdl().unwrap());
+
+	let options = TextOptions::aligned(Align::Left, win_w - 16.0);
+	let prompt = format!("> {}_", phi.console.input);
+	let mut y = height - LINE_H - 6.0;
+	if let Some(layout) = phi.layout_text(&prompt, FONT, FONT_SIZE, &options) {
+		phi.renderer.copy_text(&layout, 8.0, y, Color::RGB(255,255,255));
+	}
+
+	// The output is drawn upwards from the prompt, starting from the most
+	// recent line which is not scrolled past.
+	let end = phi.console.output.len() - phi.console.scroll;
+	let lines: Vec<String> = phi.console.output[..end].iter().rev()
+		.take((height / LINE_H) as usize)
+		.cloned()
+		.collect();
+	for line in lines {
+		y -= LINE_H;
+		if y < 0.0 {
+			break;
+		}
+		if let Some(layout) = phi.layout_text(&line, FONT, FONT_SIZE, &options) {
+			phi.renderer.copy_text(&layout, 8.0, y, Color::RGB(200,200,200));
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::Console;
+	use ::phi::ImmediateEvents;
+	use ::phi::events::EditKey;
+
+	use ::sdl2::keyboard::Keycode;
+
+	fn complete(console: &mut Console, input: &str) -> String {
+		console.input = input.to_string();
+		console.complete();
+		console.input.clone()
+	}
+
+	#[test]
+	fn complete_a_single_command() {
+		let mut console = Console::new();
+		console.register_queued("speed", "speed <pixels per second>");
+		assert_eq!(complete(&mut console, "spe"), "speed ");
+		assert_eq!(complete(&mut console, "he"), "help ");
+		assert!(console.output.is_empty());
+	}
+
+	#[test]
+	fn complete_as_far_as_the_commands_agree() {
+		let mut console = Console::new();
+		console.register_queued("speed_up", "speed_up");
+		console.register_queued("speed_down", "speed_down");
+		assert_eq!(complete(&mut console, "sp"), "speed_");
+		assert_eq!(console.output.last().unwrap(), "speed_down  speed_up");
+	}
+
+	#[test]
+	fn take_input_leaves_no_key_behind() {
+		let mut console = Console::new();
+		let mut now = ImmediateEvents::new();
+		now.keys = vec![(Keycode::F3, true), (Keycode::F12, true), (Keycode::A, false)];
+		now.text = "he".to_string();
+		now.edit_keys = vec![EditKey::Submit];
+
+		assert!(console.take_input(&mut now));
+		assert_eq!(console.input, "he");
+		assert!(now.keys.is_empty() && now.text.is_empty() && now.edit_keys.is_empty());
+	}
+
+	#[test]
+	fn complete_nothing() {
+		let mut console = Console::new();
+		assert_eq!(complete(&mut console, "zz"), "zz");
+		//? Arguments are not completed.
+		assert_eq!(complete(&mut console, "he "), "he ");
+		assert!(console.output.is_empty());
+
+		console.register_queued("speed", "speed <pixels per second>");
+		console.unregister("speed");
+		assert_eq!(complete(&mut console, "spe"), "spe");
+	}
+}
@@ -0,0 +1,424 @@
+use ::phi::{Events, Phi};
+use ::phi::data::Rectangle;
+use ::phi::text::{Align, CopyText, TextOptions};
+
+use ::std::collections::VecDeque;
+use ::std::fmt::Display;
+
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::pixels::Color;
+use ::sdl2::rect::Point;
+use ::sdl2::render::{BlendMode, Renderer};
+
+/// The key which shows and hides the debug overlay.
+pub const TOGGLE_KEY: Keycode = Keycode::F1;
+/// The key which shows and hides the shapes of `DebugDraw`.
+pub const SHAPES_KEY: Keycode = Keycode::F2;
+
+const FONT: &'static str = "assets/belligerent.ttf";
+const FONT_SIZE: i32 = 16;
+
+/// Where the overlay is drawn, and the size of its rows.
+const PANEL_X: f64 = 10.0;
+const PANEL_Y: f64 = 10.0;
+const PANEL_W: f64 = 320.0;
+const ROW_H: f64 = 22.0;
+/// The width of the sliders, on the right of the rows.
+const SLIDER_W: f64 = 140.0;
+
+/// How many frames the timings are averaged over.
+const TIMING_FRAMES: usize = 120;
+
+/// What a row of the overlay shows.
+enum RowKind {
+	/// A slider filled up to the given fraction, and the value it edits.
+	Slider(f64, String),
+	Checkbox(bool),
+	Text(String),
+}
+
+struct Row {
+	label: String,
+	kind: RowKind,
+}
+
+/// An overlay which shows and edits variables while the game runs, to tune
+/// it without recompiling. It is immediate-mode: views call `float`,
+/// `checkbox`, `choice` or `value` on every frame for every variable they
+/// want to show, always in the same order, and the overlay edits the
+/// variables in place with the mouse.
+///
+/// Nothing happens while the overlay is hidden, which is the default.
+pub struct DebugUi {
+	pub visible: bool,
+	rows: Vec<Row>,
+	/// The state of the mouse during this frame.
+	mouse: (f64, f64),
+	mouse_down: bool,
+	clicked: bool,
+	/// The slider being dragged, by the index of its row.
+	dragging: Option<usize>,
+	/// The duration of the last frames, in seconds, oldest first.
+	frame_times: VecDeque<f64>,
+}
+
+impl DebugUi {
+	pub fn new() -> DebugUi {
+		DebugUi {
+			visible: false,
+			rows: Vec::new(),
+			mouse: (0.0, 0.0),
+			mouse_down: false,
+			clicked: false,
+			dragging: None,
+			frame_times: VecDeque::new(),
+		}
+	}
+
+	/// Starts a new frame with the input of `events`. Called by `spawn`
+	/// before the view is rendered.
+	pub fn begin_frame(&mut self, events: &Events, elapsed: f64) {
+		if events.now.keys.contains(&(TOGGLE_KEY, true)) {
+			self.visible = !self.visible;
+		}
+
+		self.frame_times.push_back(elapsed);
+		if self.frame_times.len() > TIMING_FRAMES {
+			self.frame_times.pop_front();
+		}
+
+		self.rows.clear();
+		self.mouse = (events.mouse.x, events.mouse.y);
+		self.mouse_down = events.mouse.left;
+		self.clicked = events.now.mouse.left == Some(true);
+		if !self.mouse_down {
+			self.dragging = None;
+		}
+	}
+
+	/// Where the next row will be drawn.
+	fn next_bounds(&self) -> Rectangle {
+		Rectangle {
+			x: PANEL_X,
+			//? The first row shows the frame timings.
+			y: PANEL_Y + ROW_H * (self.rows.len() + 1) as f64,
+			w: PANEL_W,
+			h: ROW_H,
+		}
+	}
+
+	fn clicked_in(&self, bounds: Rectangle) -> bool {
+		let (x, y) = self.mouse;
+		self.clicked && bounds.contains(Rectangle { x: x, y: y, w: 0.0, h: 0.0 })
+	}
+
+	/// Shows `value` with a slider going from `min` to `max`, which changes
+	/// it when dragged.
+	pub fn float(&mut self, label: &str, value: &mut f64, min: f64, max: f64) {
+		if !self.visible {
+			return;
+		}
+
+		let row = self.rows.len();
+		let bounds = self.next_bounds();
+		let bar = Rectangle {
+			x: bounds.x + bounds.w - SLIDER_W,
+			w: SLIDER_W,
+			.. bounds
+		};
+
+		if self.clicked_in(bar) {
+			self.dragging = Some(row);
+		}
+		if self.dragging == Some(row) && max > min {
+			let fraction = ((self.mouse.0 - bar.x) / bar.w).max(0.0).min(1.0);
+			*value = min + fraction * (max - min);
+		}
+
+		let fraction = if max > min { (*value - min) / (max - min) } else { 0.0 };
+		self.rows.push(Row {
+			label: label.to_string(),
+			kind: RowKind::Slider(fraction.max(0.0).min(1.0), format!("{:.2}", *value)),
+		});
+	}
+
+	/// Shows `value` with a checkbox, which toggles it when clicked.
+	pub fn checkbox(&mut self, label: &str, value: &mut bool) {
+		if !self.visible {
+			return;
+		}
+
+		let bounds = self.next_bounds();
+		if self.clicked_in(bounds) {
+			*value = !*value;
+		}
+
+		self.rows.push(Row {
+			label: label.to_string(),
+			kind: RowKind::Checkbox(*value),
+		});
+	}
+
+	/// Shows which of `options` is selected by `index`. Clicking selects the
+	/// next one.
+	pub fn choice(&mut self, label: &str, index: &mut usize, options: &[&str]) {
+		if !self.visible || options.is_empty() {
+			return;
+		}
+
+		let bounds = self.next_bounds();
+		if self.clicked_in(bounds) {
+			*index = (*index + 1) % options.len();
+		}
+
+		let text = options.get(*index).cloned().unwrap_or("?").to_string();
+		self.rows.push(Row {
+			label: label.to_string(),
+			kind: RowKind::Text(text),
+		});
+	}
+
+	/// Shows a value which cannot be edited, e.g. how many entities exist.
+	pub fn value<T: Display>(&mut self, label: &str, value: T) {
+		if !self.visible {
+			return;
+		}
+
+		self.rows.push(Row {
+			label: label.to_string(),
+			kind: RowKind::Text(value.to_string()),
+		});
+	}
+
+	/// The average and longest duration of the last frames, in seconds.
+	pub fn frame_timings(&self) -> (f64, f64) {
+		if self.frame_times.is_empty() {
+			return (0.0, 0.0);
+		}
+		let total = self.frame_times.iter().fold(0.0, |total, t| total + t);
+		let longest = self.frame_times.iter().cloned().fold(0.0, f64::max);
+		(total / self.frame_times.len() as f64, longest)
+	}
+}
+
+/// A shape queued by `DebugDraw`.
+enum Shape {
+	Rect(Rectangle),
+	Circle((f64, f64), f64),
+	Line((f64, f64), (f64, f64)),
+	Arrow((f64, f64), (f64, f64)),
+	Label((f64, f64), String),
+}
+
+/// A queue of shapes drawn over the frame, to visualise what is normally
+/// invisible: hitboxes, velocities, cells of a spatial grid... Anything can
+/// queue shapes while updating; they are drawn at the end of the frame,
+/// after the view, and then forgotten.
+///
+/// Coordinates are the ones used by views to draw. Nothing is queued while
+/// the shapes are hidden, which is the default.
+pub struct DebugDraw {
+	pub enabled: bool,
+	shapes: Vec<(Shape, Color)>,
+}
+
+impl DebugDraw {
+	pub fn new() -> DebugDraw {
+		DebugDraw {
+			enabled: false,
+			shapes: Vec::new(),
+		}
+	}
+
+	/// Starts a new frame, forgetting the shapes of the previous one if they
+	/// were not drawn. Called by `spawn` before the view is rendered.
+	pub fn begin_frame(&mut self, events: &Events) {
+		if events.now.keys.contains(&(SHAPES_KEY, true)) {
+			self.enabled = !self.enabled;
+		}
+		self.shapes.clear();
+	}
+
+	fn push(&mut self, shape: Shape, color: Color) {
+		if self.enabled {
+			self.shapes.push((shape, color));
+		}
+	}
+
+	/// The outline of `rect`.
+	pub fn rect(&mut self, rect: Rectangle, color: Color) {
+		self.push(Shape::Rect(rect), color);
+	}
+
+	/// The outline of a circle.
+	pub fn circle(&mut self, center: (f64, f64), radius: f64, color: Color) {
+		self.push(Shape::Circle(center, radius), color);
+	}
+
+	pub fn line(&mut self, from: (f64, f64), to: (f64, f64), color: Color) {
+		self.push(Shape::Line(from, to), color);
+	}
+
+	/// A line with a head at `to`, e.g. for a velocity.
+	pub fn arrow(&mut self, from: (f64, f64), to: (f64, f64), color: Color) {
+		self.push(Shape::Arrow(from, to), color);
+	}
+
+	/// Text whose top-left corner is at `pos`.
+	pub fn label(&mut self, pos: (f64, f64), text: &str, color: Color) {
+		self.push(Shape::Label(pos, text.to_string()), color);
+	}
+}
+
+fn point((x, y): (f64, f64)) -> Point {
+	Point::new(x as i32, y as i32)
+}
+
+/// Draws the outline of a shape of `DebugDraw`. Labels are drawn by
+/// `render`, which can lay text out.
+fn draw_shape(renderer: &mut Renderer, shape: &Shape) {
+	match *shape {
+		Shape::Rect(rect) => {
+			if let Some(rect) = rect.to_sdl() {
+				renderer.draw_rect(rect);
+			}
+		},
+		Shape::Circle((x, y), radius) => {
+			//? Enough segments for the circle to look round at the sizes of
+			//? hitboxes.
+			let segments = 24;
+			let points: Vec<Point> = (0..segments + 1)
+				.map(|i| {
+					let angle = i as f64 / segments as f64 * 2.0 * ::std::f64::consts::PI;
+					point((x + radius * angle.cos(), y + radius * angle.sin()))
+				})
+				.collect();
+			renderer.draw_lines(&points);
+		},
+		Shape::Line(from, to) => {
+			renderer.draw_line(point(from), point(to));
+		},
+		Shape::Arrow(from, to) => {
+			renderer.draw_line(point(from), point(to));
+
+			let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+			let len = (dx * dx + dy * dy).sqrt();
+			if len > 0.0 {
+				// The head is made of two lines, going back from the tip at
+				// 30 degrees on each side.
+				let head = (len / 3.0).min(8.0);
+				let (ux, uy) = (dx / len, dy / len);
+				let (sin, cos) = (::std::f64::consts::PI / 6.0).sin_cos();
+				for &side in &[1.0, -1.0] {
+					let (hx, hy) = (ux * cos - uy * sin * side, uy * cos + ux * sin * side);
+					renderer.draw_line(point(to), point((to.0 - hx * head, to.1 - hy * head)));
+				}
+			}
+		},
+		Shape::Label(..) => {},
+	}
+}
+
+/// Draws the shapes queued in `phi.debug_draw` and the overlay of
+/// `phi.debug` over the frame, if they are visible. Called by `spawn` after
+/// the view is rendered.
+pub fn render(phi: &mut Phi) {
+	let shapes = ::std::mem::replace(&mut phi.debug_draw.shapes, Vec::new());
+	for &(ref shape, color) in &shapes {
+		phi.renderer.set_draw_color(color);
+		draw_shape(&mut phi.renderer, shape);
+
+		if let Shape::Label(pos, ref text) = *shape {
+			if let Some(layout) = phi.layout_text(text, FONT, FONT_SIZE, &TextOptions::default()) {
+				phi.renderer.copy_text(&layout, pos.0, pos.1, color);
+			}
+		}
+	}
+
+	if !phi.debug.visible {
+		return;
+	}
+
+	//? The rows are taken out of the overlay, so that it is not borrowed
+	//? while drawing with `phi`.
+	let rows = ::std::mem::replace(&mut phi.debug.rows, Vec::new());
+	let (average, longest) = phi.debug.frame_timings();
+
+	phi.renderer.set_blend_mode(BlendMode::Blend);
+	phi.renderer.set_draw_color(Color::RGBA(0,0,0,180));
+	phi.renderer.fill_rect(Rectangle {
+		x: PANEL_X - 4.0,
+		y: PANEL_Y - 4.0,
+		w: PANEL_W + 8.0,
+		h: ROW_H * (rows.len() + 1) as f64 + 8.0,
+	}.to_sdl().unwrap());
+	phi.renderer.set_blend_mode(BlendMode::None);
+
+	let white = Color::RGB(255,255,255);
+	let accent = Color::RGB(255,204,51);
+	let left = TextOptions::aligned(Align::Left, PANEL_W);
+	let right = TextOptions::aligned(Align::Right, PANEL_W);
+
+	let fps = if average > 0.0 { 1.0 / average } else { 0.0 };
+	let timings = format!("{:.0} fps  {:.1} ms avg  {:.1} ms max", fps, average * 1000.0, longest * 1000.0);
+	if let Some(layout) = phi.layout_text(&timings, FONT, FONT_SIZE, &left) {
+		phi.renderer.copy_text(&layout, PANEL_X, PANEL_Y, accent);
+	}
+
+	for (i, row) in rows.iter().enumerate() {
+		let y = PANEL_Y + ROW_H * (i + 1) as f64;
+
+		if let Some(layout) = phi.layout_text(&row.label, FONT, FONT_SIZE, &left) {
+			phi.renderer.copy_text(&layout, PANEL_X, y, white);
+		}
+
+		match row.kind {
+			RowKind::Slider(fraction, ref text) => {
+				let bar = Rectangle {
+					x: PANEL_X + PANEL_W - SLIDER_W,
+					y: y + 3.0,
+					w: SLIDER_W,
+					h: ROW_H - 6.0,
+				};
+				phi.renderer.set_draw_color(Color::RGB(70,15,70));
+				phi.renderer.fill_rect(bar.to_sdl().unwrap());
+				//? A fill narrower than a pixel has no SDL rectangle.
+				if let Some(fill) = (Rectangle { w: bar.w * fraction, .. bar }).to_sdl() {
+					phi.renderer.set_draw_color(Color::RGB(140,30,140));
+					phi.renderer.fill_rect(fill);
+				}
+
+				// The value is written over the slider.
+				let options = TextOptions::aligned(Align::Center, SLIDER_W);
+				if let Some(layout) = phi.layout_text(text, FONT, FONT_SIZE, &options) {
+					phi.renderer.copy_text(&layout, bar.x, y, white);
+				}
+			},
+			RowKind::Checkbox(checked) => {
+				let size = ROW_H - 8.0;
+				let rect = Rectangle {
+					x: PANEL_X + PANEL_W - size,
+					y: y + 4.0,
+					w: size,
+					h: size,
+				};
+				phi.renderer.set_draw_color(white);
+				phi.renderer.draw_rect(rect.to_sdl().unwrap());
+				if checked {
+					phi.renderer.set_draw_color(accent);
+					phi.renderer.fill_rect(Rectangle {
+						x: rect.x + 3.0,
+						y: rect.y + 3.0,
+						w: size - 6.0,
+						h: size - 6.0,
+					}.to_sdl().unwrap());
+				}
+			},
+			RowKind::Text(ref text) => {
+				if let Some(layout) = phi.layout_text(text, FONT, FONT_SIZE, &right) {
+					phi.renderer.copy_text(&layout, PANEL_X, y, accent);
+				}
+			},
+		}
+	}
+}
@@ -4,91 +4,465 @@ macro_rules! struct_events {
     (
         keyboard: { $( $k_alias:ident : $k_sdl:ident ),* },
         // match against a pattern
-        else: { $( $e_alias:ident: $e_sdl:pat ),* }
+        else: { $( $e_alias:ident: $e_sdl:pat ),* },
+        // every event matching the pattern pushes a value, built from the
+        // fields it captured, to a queue which is emptied every frame
+        queue: { $( $q_alias:ident : $q_ty:ty = $q_sdl:pat => $q_value:expr ),* },
+        // state which persists across frames, with its initial value and
+        // the events which change it
+        held: { $( $h_alias:ident : $h_ty:ty = $h_init:expr ; {
+            $( $h_sdl:pat => $h_value:expr ),*
+        } ),* }
 
     ) => {
         use ::sdl2::EventPump;
+        use ::sdl2::controller::{Axis, Button};
+        use ::sdl2::mouse::Mouse as MouseButton;
+        use ::sdl2::keyboard::Keycode;
+        use ::phi::events::{EditKey, Gamepads, Mouse, MouseNow};
+        use ::sdl2::keyboard::TextInputUtil;
 
         pub struct ImmediateEvents {
-            resize: Option<(u32, u32)>,
+            // The new size of the window, if it was resized this frame.
+            pub resize: Option<(u32, u32)>,
+            // Every key which was pressed (true) or released (false) this
+            // frame, whether or not it has an alias.
+            pub keys: Vec<(Keycode, bool)>,
+            // Every controller button pressed or released this frame, along
+            // with the player holding the controller.
+            pub buttons: Vec<(usize, Button, bool)>,
+            // Every new position of a controller axis, as reported by SDL.
+            pub axes: Vec<(usize, Axis, i16)>,
+            // The players whose controller was plugged in or out.
+            pub gamepads_added: Vec<usize>,
+            pub gamepads_removed: Vec<usize>,
+            pub mouse: MouseNow,
+            // Where fingers touched or left the screen, in logical pixels.
+            pub touches_started: Vec<(f64, f64)>,
+            pub touches_ended: Vec<(f64, f64)>,
+            // The text typed during this frame, while text input is active.
+            pub text: String,
+            // Editing keys pressed this frame, including the presses which
+            // the OS repeats while the key is held.
+            pub edit_keys: Vec<EditKey>,
+            // Whether the player pressed something while a replay was being
+            // played back, in which case the input was ignored.
+            pub live_input: bool,
             // For every keyboard event, we have an Option<bool>
             // Some(true) => was just pressed
             // Some(false) => was just released
             // None       => Nothing happening _now_
             $( pub $k_alias: Option<bool> , )*
-            $( pub $e_alias: bool),*
+            $( pub $e_alias: bool , )*
+            // Values pushed by the events of the `queue` section, in the
+            // order in which they happened.
+            $( pub $q_alias: Vec<$q_ty> , )*
+            // For every `held` state, its new value if it changed.
+            $( pub $h_alias: Option<$h_ty> ),*
         }
 
         impl ImmediateEvents {
             pub fn new() -> ImmediateEvents {
                 ImmediateEvents {
                     resize: None,
+                    keys: Vec::new(),
+                    buttons: Vec::new(),
+                    axes: Vec::new(),
+                    gamepads_added: Vec::new(),
+                    gamepads_removed: Vec::new(),
+                    mouse: MouseNow::new(),
+                    touches_started: Vec::new(),
+                    touches_ended: Vec::new(),
+                    text: String::new(),
+                    edit_keys: Vec::new(),
+                    live_input: false,
                     // When reinit'd, nothing has happened yet, so
                     // all are set to None
                     $( $k_alias: None , )*
-                    $( $e_alias: false  ),*
+                    $( $e_alias: false , )*
+                    $( $q_alias: Vec::new() , )*
+                    $( $h_alias: None ),*
                 }
             }
+
+            /// Whether `key` was just pressed (`Some(true)`), just released
+            /// (`Some(false)`) or neither (`None`) during this frame.
+            pub fn key(&self, key: Keycode) -> Option<bool> {
+                self.keys.iter().rev()
+                    .find(|&&(k, _)| k == key)
+                    .map(|&(_, pressed)| pressed)
+            }
         }
 
         pub struct Events {
             pump: EventPump,
             pub now: ImmediateEvents,
+            // Every key which is held, along with the time at which it was
+            // pressed.
+            keys_held: ::std::collections::HashMap<Keycode, f64>,
+            // The keys pressed recently, for combos and buffering.
+            pub history: ::phi::events::InputHistory<Keycode>,
+            // The number of seconds spent in the game, advanced by `advance`
+            // before every pump. It only moves between frames, so that it is
+            // the same when a replay is played back.
+            pub time: f64,
+            pub gamepads: Gamepads,
+            pub mouse: Mouse,
+            // The fingers currently on the screen, by id, in logical pixels.
+            pub touches: ::std::collections::HashMap<i64, (f64, f64)>,
+            text_input: TextInputUtil,
+            // The text being composed with an input method, which is not
+            // part of the typed text until it is committed, along with the
+            // position of the cursor inside of it.
+            pub composition: Option<(String, i32)>,
             // true => pressed
             // false => not pressed
-            $( pub $k_alias: bool), *
+            $( pub $k_alias: bool , )*
+            $( pub $h_alias: $h_ty ),*
         }
 
         impl Events {
-            pub fn new(pump: EventPump) -> Events {
+            pub fn new(pump: EventPump, gamepads: Gamepads, text_input: TextInputUtil) -> Events {
+                // SDL enables text input on startup; it should only be on
+                // while a text field has the focus.
+                text_input.stop();
+
                 Events {
                     pump: pump,
                     now: ImmediateEvents::new(),
+                    keys_held: ::std::collections::HashMap::new(),
+                    history: ::phi::events::InputHistory::new(),
+                    time: 0.0,
+                    gamepads: gamepads,
+                    mouse: Mouse::new(),
+                    touches: ::std::collections::HashMap::new(),
+                    text_input: text_input,
+                    composition: None,
                     // By default, init every key to not pressed
-                    $( $k_alias: false),*
+                    $( $k_alias: false , )*
+                    $( $h_alias: $h_init ),*
                 }
             }
 
-            pub fn pump(&mut self, renderer: &mut ::sdl2::render::Renderer) {
+            /// Whether `key` is currently held down.
+            pub fn is_held(&self, key: Keycode) -> bool {
+                self.keys_held.contains_key(&key)
+            }
+
+            /// The time at which `key` was pressed, if it is held.
+            pub fn pressed_at(&self, key: Keycode) -> Option<f64> {
+                self.keys_held.get(&key).cloned()
+            }
+
+            /// For how many seconds `key` has been held, or 0 if it is not.
+            pub fn held_for(&self, key: Keycode) -> f64 {
+                self.pressed_at(key).map(|at| self.time - at).unwrap_or(0.0)
+            }
+
+            /// Moves the clock forward by `dt` seconds. Called by `spawn`
+            /// before pumping the events of a new frame.
+            pub fn advance(&mut self, dt: f64) {
+                self.time += dt;
+            }
+
+            /// Every key currently held down, in no particular order.
+            pub fn held_keys(&self) -> Vec<Keycode> {
+                self.keys_held.keys().cloned().collect()
+            }
+
+            /// Starts reporting typed text in `now.text`, and shows the
+            /// on-screen keyboard or the input method window if any.
+            pub fn start_text_input(&mut self) {
+                self.text_input.start();
+            }
+
+            pub fn stop_text_input(&mut self) {
+                self.text_input.stop();
+                self.composition = None;
+            }
+
+            pub fn is_text_input_active(&self) -> bool {
+                self.text_input.is_active()
+            }
+
+            // The following methods apply a single input to the state. They
+            // are used both when pumping SDL's events and when playing a
+            // replay back.
+
+            pub fn press_key(&mut self, key: Keycode) {
+                // A key which is already held is being repeated by the
+                // system; see `InputMap` for repeating actions instead.
+                if self.keys_held.contains_key(&key) {
+                    return;
+                }
+                self.keys_held.insert(key, self.time);
+                self.history.push(self.time, key);
+                self.now.keys.push((key, true));
+
+                match key {
+                    // $( .. ),* containing $k_sdl and $k_alias means
+                    // "for every element ($k_alias : $k_sdl) pair,
+                    // check whether the keycode is $k_sdl. If
+                    // it is, then set the $k_alias fields to true.
+                    $(
+                        Keycode::$k_sdl => {
+                            // Key Pressed
+                            self.now.$k_alias = Some(true);
+                            self.$k_alias = true;
+                        }
+                    ),* // and add a comma after each option
+                    _ => {}
+                }
+            }
+
+            pub fn release_key(&mut self, key: Keycode) {
+                self.keys_held.remove(&key);
+                self.now.keys.push((key, false));
+
+                match key {
+                    $(
+                        Keycode::$k_sdl => {
+                            // Key released
+                            self.now.$k_alias = Some(false);
+                            self.$k_alias = false;
+                        }
+                    ),*
+                    _ => {}
+                }
+            }
+
+            pub fn set_button(&mut self, player: usize, button: Button, pressed: bool) {
+                self.gamepads.set_button(player, button, pressed);
+                self.now.buttons.push((player, button, pressed));
+            }
+
+            pub fn set_axis(&mut self, player: usize, axis: Axis, value: i16) {
+                self.gamepads.set_axis(player, axis, value);
+                self.now.axes.push((player, axis, value));
+            }
+
+            pub fn move_mouse(&mut self, x: f64, y: f64) {
+                self.mouse.x = x;
+                self.mouse.y = y;
+                self.now.mouse.moved = true;
+            }
+
+            pub fn set_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+                self.mouse.set_button(&mut self.now.mouse, button, pressed);
+            }
+
+            pub fn type_text(&mut self, text: &str) {
+                self.now.text.push_str(text);
+                self.composition = None;
+            }
+
+            /// Releases every key and button which is held, as if the player
+            /// had let go of everything. The releases are reported in `now`.
+            pub fn release_all(&mut self) {
+                let held: Vec<Keycode> = self.keys_held.keys().cloned().collect();
+                for key in held {
+                    self.release_key(key);
+                }
+
+                self.gamepads.release_all();
+                self.set_mouse_button(MouseButton::Left, false);
+                self.set_mouse_button(MouseButton::Middle, false);
+                self.set_mouse_button(MouseButton::Right, false);
+                self.touches.clear();
+            }
+
+            /// Updates the queues and held states declared when invoking the
+            /// macro. The same event may be relevant to several of them, so
+            /// each one gets its own copy.
+            fn record_declared(&mut self, event: &::sdl2::event::Event) {
+                use ::sdl2::event::Event::*;
+                #[allow(unused_imports)]
+                use ::sdl2::event::WindowEventId;
+
+                $(
+                    if let $q_sdl = event.clone() {
+                        self.now.$q_alias.push($q_value);
+                    }
+                )*
+
+                $( $(
+                    if let $h_sdl = event.clone() {
+                        let value = $h_value;
+                        if self.$h_alias != value {
+                            self.now.$h_alias = Some(value.clone());
+                        }
+                        self.$h_alias = value;
+                    }
+                )* )*
+            }
+
+            /// Starts a new frame: forgets what happened during the previous
+            /// one, but not what is held.
+            fn clear_now(&mut self) {
                 self.now = ImmediateEvents::new();
+            }
+
+            pub fn pump(&mut self, renderer: &mut ::sdl2::render::Renderer) {
+                self.clear_now();
+
+                //? The iterator borrows the pump, so the events are collected
+                //? before handling them with methods which borrow `self`.
+                let events: Vec<_> = self.pump.poll_iter().collect();
 
-                for event in self.pump.poll_iter() {
+                for event in events {
                     use ::sdl2::event::Event::*;
                     use ::sdl2::event::WindowEventId::Resized;
-                    use ::sdl2::keyboard::Keycode::*;
+
+                    self.record_declared(&event);
 
                     match event {
                         Window { win_event_id: Resized, .. } => {
                             self.now.resize = Some(renderer.output_size().unwrap());
                         },
-                        KeyDown { keycode, .. } => match keycode {
-                            // $( .. ),* containing $k_sdl and $k_alias means
-                            // "for every element ($k_alias : $k_sdl) pair,
-                            // check whether the keycode is Some($k_sdl). If
-                            // it is, then set the $k_alias fields to true.
-                            $(
-                                Some($k_sdl) => {
-                                    // Prevent multiple presses when keeping a key down
-                                    // Was previously not pressed?
-                                    if !self.$k_alias {
-                                        // Key Pressed
-                                        self.now.$k_alias = Some(true);
-                                    }
-                                    self.$k_alias = true;
-                                }
-                            ),* // and add a comma after each option
-                            _ => {}
-                        },
-
-                        KeyUp { keycode, .. } => match keycode {
-                            $(
-                                Some($k_sdl) => {
-                                    // Key released
-                                    self.now.$k_alias = Some(false);
-                                    self.$k_alias = false;
-                                }
-                            ),*
-                            _ => {}
+                        KeyDown { keycode: Some(key), .. } => {
+                            // Editing keys are reported on every repeat.
+                            if let Some(edit) = EditKey::from_keycode(key) {
+                                self.now.edit_keys.push(edit);
+                            }
+                            self.press_key(key);
+                        },
+
+                        KeyUp { keycode: Some(key), .. } => {
+                            self.release_key(key);
+                        },
+
+                        TextInput { text, .. } => {
+                            self.type_text(&text);
+                        },
+
+                        TextEditing { text, start, .. } => {
+                            self.composition =
+                                if text.is_empty() { None }
+                                else { Some((text, start)) };
+                        },
+
+                        MouseMotion { x, y, .. } => {
+                            let (x, y) = ::phi::events::to_logical(renderer, x as f64, y as f64);
+                            self.move_mouse(x, y);
+                        },
+
+                        MouseButtonDown { mouse_btn, x, y, .. } => {
+                            let (x, y) = ::phi::events::to_logical(renderer, x as f64, y as f64);
+                            self.move_mouse(x, y);
+                            self.set_mouse_button(mouse_btn, true);
+                        },
+
+                        MouseButtonUp { mouse_btn, x, y, .. } => {
+                            let (x, y) = ::phi::events::to_logical(renderer, x as f64, y as f64);
+                            self.move_mouse(x, y);
+                            self.set_mouse_button(mouse_btn, false);
+                        },
+
+                        MouseWheel { x, y, .. } => {
+                            self.now.mouse.wheel.0 += x;
+                            self.now.mouse.wheel.1 += y;
+                        },
+
+                        // Touch positions are normalized to the window, so we
+                        // scale them by the size of the logical screen.
+                        FingerDown { finger_id, x, y, .. } => {
+                            let pos = ::phi::events::touch_to_logical(renderer, x as f64, y as f64);
+                            self.touches.insert(finger_id, pos);
+                            self.now.touches_started.push(pos);
+                        },
+
+                        FingerMotion { finger_id, x, y, .. } => {
+                            let pos = ::phi::events::touch_to_logical(renderer, x as f64, y as f64);
+                            self.touches.insert(finger_id, pos);
+                        },
+
+                        FingerUp { finger_id, x, y, .. } => {
+                            let pos = ::phi::events::touch_to_logical(renderer, x as f64, y as f64);
+                            self.touches.remove(&finger_id);
+                            self.now.touches_ended.push(pos);
+                        },
+
+                        ControllerDeviceAdded { which, .. } => {
+                            // `which` is the index of the device, which is
+                            // only meaningful until the controller is opened.
+                            if let Some(player) = self.gamepads.add(which) {
+                                self.now.gamepads_added.push(player);
+                            }
+                        },
+
+                        ControllerDeviceRemoved { which, .. } => {
+                            // Here, `which` is the id of the opened instance.
+                            if let Some(player) = self.gamepads.remove(which) {
+                                self.now.gamepads_removed.push(player);
+                            }
+                        },
+
+                        ControllerButtonDown { which, button, .. } => {
+                            if let Some(player) = self.gamepads.player_of(which) {
+                                self.set_button(player, button, true);
+                            }
+                        },
+
+                        ControllerButtonUp { which, button, .. } => {
+                            if let Some(player) = self.gamepads.player_of(which) {
+                                self.set_button(player, button, false);
+                            }
+                        },
+
+                        ControllerAxisMotion { which, axis, value, .. } => {
+                            if let Some(player) = self.gamepads.player_of(which) {
+                                self.set_axis(player, axis, value);
+                            }
+                        },
+
+                        $( 
+                            $e_sdl => {
+                                self.now.$e_alias = true;
+                            }
+                        ,)*
+
+                        _ => {}
+                    }
+                }
+            }
+
+            /// Replaces the pump while a replay is played back: the input of
+            /// the frame comes from `frame`, and SDL's events are only used to
+            /// keep the window and the controllers up to date. `live_input` is
+            /// set if the player pressed anything, so that a demo can be
+            /// interrupted.
+            pub fn pump_replay(&mut self, renderer: &mut ::sdl2::render::Renderer,
+                               frame: &::phi::replay::FrameInput) {
+                self.clear_now();
+
+                let events: Vec<_> = self.pump.poll_iter().collect();
+
+                for event in events {
+                    use ::sdl2::event::Event::*;
+                    use ::sdl2::event::WindowEventId::Resized;
+
+                    self.record_declared(&event);
+
+                    match event {
+                        Window { win_event_id: Resized, .. } => {
+                            self.now.resize = Some(renderer.output_size().unwrap());
+                        },
+
+                        KeyDown { .. } | MouseButtonDown { .. } |
+                        ControllerButtonDown { .. } | FingerDown { .. } => {
+                            self.now.live_input = true;
+                        },
+
+                        ControllerDeviceAdded { which, .. } => {
+                            if let Some(player) = self.gamepads.add(which) {
+                                self.now.gamepads_added.push(player);
+                            }
+                        },
+
+                        ControllerDeviceRemoved { which, .. } => {
+                            if let Some(player) = self.gamepads.remove(which) {
+                                self.now.gamepads_removed.push(player);
+                            }
                         },
 
                         $( 
@@ -100,10 +474,387 @@ macro_rules! struct_events {
                         _ => {}
                     }
                 }
+
+                frame.apply(self);
             }
         }
     }
 }
+use ::std::collections::{HashMap, HashSet, VecDeque};
+
+use ::sdl2::GameControllerSubsystem;
+use ::sdl2::controller::{Axis, Button, GameController};
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::mouse::Mouse as MouseButton;
+use ::sdl2::render::Renderer;
+
+/// The size of the screen in logical pixels, i.e. the coordinate system in
+/// which views draw: the logical size of the renderer if one was set, or
+/// its output size otherwise.
+fn logical_size(renderer: &Renderer) -> (f64, f64) {
+    match renderer.logical_size() {
+        (0, 0) => {
+            let (w, h) = renderer.output_size().unwrap();
+            (w as f64, h as f64)
+        },
+        (w, h) => (w as f64, h as f64),
+    }
+}
+
+/// Converts a position in window coordinates, as reported by mouse events,
+/// into logical pixels. They differ on high-DPI screens, and whenever a
+/// logical size is set.
+pub fn to_logical(renderer: &Renderer, x: f64, y: f64) -> (f64, f64) {
+    let (logical_w, logical_h) = logical_size(renderer);
+    let (window_w, window_h) = renderer.window()
+        .map(|window| window.size())
+        .unwrap_or((logical_w as u32, logical_h as u32));
+
+    if window_w == 0 || window_h == 0 {
+        return (x, y);
+    }
+
+    (x * logical_w / window_w as f64, y * logical_h / window_h as f64)
+}
+
+/// Converts a touch position, normalized between 0 and 1, into logical
+/// pixels.
+pub fn touch_to_logical(renderer: &Renderer, x: f64, y: f64) -> (f64, f64) {
+    let (w, h) = logical_size(renderer);
+    (x * w, y * h)
+}
+
+/// Keys which edit text. They are reported every time the OS repeats them,
+/// so that holding backspace keeps erasing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditKey {
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Submit,
+}
+
+impl EditKey {
+    pub fn from_keycode(key: Keycode) -> Option<EditKey> {
+        match key {
+            Keycode::Backspace => Some(EditKey::Backspace),
+            Keycode::Delete => Some(EditKey::Delete),
+            Keycode::Left => Some(EditKey::Left),
+            Keycode::Right => Some(EditKey::Right),
+            Keycode::Home => Some(EditKey::Home),
+            Keycode::End => Some(EditKey::End),
+            Keycode::Return | Keycode::KpEnter => Some(EditKey::Submit),
+            _ => None,
+        }
+    }
+}
+
+/// The position of the mouse, in logical pixels, and its held buttons.
+pub struct Mouse {
+    pub x: f64,
+    pub y: f64,
+    pub left: bool,
+    pub middle: bool,
+    pub right: bool,
+}
+
+impl Mouse {
+    pub fn new() -> Mouse {
+        Mouse {
+            x: 0.0,
+            y: 0.0,
+            left: false,
+            middle: false,
+            right: false,
+        }
+    }
+
+    /// Records that `button` was pressed or released, both in the held state
+    /// and in what happened during this frame.
+    pub fn set_button(&mut self, now: &mut MouseNow, button: MouseButton, pressed: bool) {
+        let (held, just) = match button {
+            MouseButton::Left => (&mut self.left, &mut now.left),
+            MouseButton::Middle => (&mut self.middle, &mut now.middle),
+            MouseButton::Right => (&mut self.right, &mut now.right),
+            _ => return,
+        };
+
+        if *held != pressed {
+            *just = Some(pressed);
+        }
+        *held = pressed;
+    }
+}
+
+/// What the mouse did during this frame. Buttons follow the same model as
+/// keys: `Some(true)` when just pressed, `Some(false)` when just released.
+pub struct MouseNow {
+    pub moved: bool,
+    pub left: Option<bool>,
+    pub middle: Option<bool>,
+    pub right: Option<bool>,
+    /// How far the wheel was scrolled horizontally and vertically.
+    pub wheel: (i32, i32),
+}
+
+impl MouseNow {
+    pub fn new() -> MouseNow {
+        MouseNow {
+            moved: false,
+            left: None,
+            middle: None,
+            right: None,
+            wheel: (0, 0),
+        }
+    }
+}
+
+/// How many controllers may be assigned to players at the same time.
+pub const MAX_PLAYERS: usize = 4;
+
+/// A controller which was assigned to a player, along with its state.
+struct Gamepad {
+    instance_id: i32,
+    // Kept open for as long as the controller is plugged in; SDL stops
+    // reporting its events once it is closed.
+    _controller: GameController,
+    buttons: HashSet<Button>,
+    axes: HashMap<Axis, i16>,
+}
+
+/// The controllers currently plugged in, indexed by player. A controller
+/// takes the first free player slot when it is plugged in and keeps it until
+/// it is unplugged, so that plugging a second controller in does not change
+/// who controls what.
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    players: Vec<Option<Gamepad>>,
+    /// Fraction of an axis' range, around its center, which is reported as
+    /// zero. Worn sticks rarely return exactly to the center.
+    pub deadzone: f64,
+}
+
+impl Gamepads {
+    pub fn new(subsystem: GameControllerSubsystem) -> Gamepads {
+        Gamepads {
+            subsystem: subsystem,
+            players: (0..MAX_PLAYERS).map(|_| None).collect(),
+            deadzone: 0.2,
+        }
+    }
+
+    /// Opens the controller with the given device index and assigns it to
+    /// the first free player, whose index is returned.
+    pub fn add(&mut self, device_index: i32) -> Option<usize> {
+        let slot = match self.players.iter().position(|p| p.is_none()) {
+            Some(slot) => slot,
+            None => return None,
+        };
+        let controller = match self.subsystem.open(device_index as u32) {
+            Ok(controller) => controller,
+            Err(_) => return None,
+        };
+
+        // The bindings do not give the instance id of a controller, but it is
+        // that of its joystick, which SDL only opens once.
+        let instance_id = match self.subsystem.sdl().joystick()
+            .and_then(|joysticks| joysticks.open(device_index as u32)) {
+            Ok(joystick) => joystick.instance_id(),
+            Err(_) => return None,
+        };
+        if self.player_of(instance_id).is_some() {
+            // SDL reports the controllers which are plugged in at startup,
+            // which may already have been opened.
+            return None;
+        }
+
+        self.players[slot] = Some(Gamepad {
+            instance_id: instance_id,
+            _controller: controller,
+            buttons: HashSet::new(),
+            axes: HashMap::new(),
+        });
+        Some(slot)
+    }
+
+    /// Frees the slot of the controller with the given instance id, and
+    /// returns the player it was assigned to.
+    pub fn remove(&mut self, instance_id: i32) -> Option<usize> {
+        let player = self.player_of(instance_id);
+        if let Some(player) = player {
+            self.players[player] = None;
+        }
+        player
+    }
+
+    pub fn player_of(&self, instance_id: i32) -> Option<usize> {
+        self.players.iter().position(|p| match *p {
+            Some(ref pad) => pad.instance_id == instance_id,
+            None => false,
+        })
+    }
+
+    pub fn is_connected(&self, player: usize) -> bool {
+        self.players.get(player).map(|p| p.is_some()).unwrap_or(false)
+    }
+
+    pub fn set_button(&mut self, player: usize, button: Button, pressed: bool) {
+        if let Some(&mut Some(ref mut pad)) = self.players.get_mut(player) {
+            if pressed { pad.buttons.insert(button); }
+            else { pad.buttons.remove(&button); }
+        }
+    }
+
+    pub fn set_axis(&mut self, player: usize, axis: Axis, value: i16) {
+        if let Some(&mut Some(ref mut pad)) = self.players.get_mut(player) {
+            pad.axes.insert(axis, value);
+        }
+    }
+
+    /// Forgets which buttons are held and where the sticks are, as if every
+    /// controller had been left alone.
+    pub fn release_all(&mut self) {
+        for pad in self.players.iter_mut().filter_map(|p| p.as_mut()) {
+            pad.buttons.clear();
+            pad.axes.clear();
+        }
+    }
+
+    /// Whether the given player holds `button` down.
+    pub fn button_held(&self, player: usize, button: Button) -> bool {
+        match self.players.get(player) {
+            Some(&Some(ref pad)) => pad.buttons.contains(&button),
+            _ => false,
+        }
+    }
+
+    /// The position of `axis` for the given player, between -1 and 1, or 0
+    /// inside of the deadzone. The range outside of the deadzone is
+    /// stretched, so that the value grows smoothly from 0.
+    pub fn axis(&self, player: usize, axis: Axis) -> f64 {
+        let raw = match self.players.get(player) {
+            Some(&Some(ref pad)) => pad.axes.get(&axis).cloned().unwrap_or(0),
+            _ => 0,
+        };
+
+        // The negative range is one unit larger than the positive one.
+        let value = (raw as f64 / 32767.0).max(-1.0).min(1.0);
+        if value.abs() < self.deadzone {
+            0.0
+        } else {
+            value.signum() * (value.abs() - self.deadzone) / (1.0 - self.deadzone)
+        }
+    }
+}
+
+/// How long, in seconds, presses are kept in an `InputHistory`.
+pub const HISTORY_AGE: f64 = 2.0;
+
+#[derive(Clone, Copy, Debug)]
+struct Press<T> {
+    time: f64,
+    input: T,
+    /// Whether the press was already used by `take_buffered`.
+    consumed: bool,
+}
+
+/// The inputs pressed during the last `HISTORY_AGE` seconds, oldest first,
+/// along with the time at which they were pressed. `Events` keeps one for
+/// keys and `InputMap` one for actions.
+pub struct InputHistory<T> {
+    presses: VecDeque<Press<T>>,
+}
+
+impl<T: Copy + PartialEq> InputHistory<T> {
+    pub fn new() -> InputHistory<T> {
+        InputHistory {
+            presses: VecDeque::new(),
+        }
+    }
+
+    /// Remembers that `input` was pressed at `time`, and forgets the
+    /// presses which are too old to matter.
+    pub fn push(&mut self, time: f64, input: T) {
+        while self.presses.front().map(|p| p.time < time - HISTORY_AGE).unwrap_or(false) {
+            self.presses.pop_front();
+        }
+        self.presses.push_back(Press {
+            time: time,
+            input: input,
+            consumed: false,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.presses.clear();
+    }
+
+    /// The presses, oldest first.
+    pub fn iter<'a>(&'a self) -> Box<Iterator<Item=(f64, T)> + 'a> {
+        Box::new(self.presses.iter().map(|p| (p.time, p.input)))
+    }
+
+    /// Whether `input` was pressed during the last `window` seconds before
+    /// `now`, and was not used yet. If so, the press is used up, so that it
+    /// triggers a single action.
+    ///
+    /// This lets a press made slightly too early, e.g. while a weapon is
+    /// cooling down, count once the game is ready for it.
+    pub fn take_buffered(&mut self, input: T, now: f64, window: f64) -> bool {
+        //? The most recent press is used, so that older ones stay available
+        //? for combos.
+        match self.presses.iter_mut().rev()
+            .take_while(|p| p.time >= now - window)
+            .find(|p| p.input == input && !p.consumed)
+        {
+            Some(press) => {
+                press.consumed = true;
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+/// A sequence of inputs which must be pressed in order, one right after
+/// the other, within `window` seconds; e.g. up, up, down, down.
+#[derive(Clone, Debug)]
+pub struct Combo<T> {
+    pub steps: Vec<T>,
+    pub window: f64,
+}
+
+impl<T: Copy + PartialEq> Combo<T> {
+    pub fn new(steps: Vec<T>, window: f64) -> Combo<T> {
+        Combo {
+            steps: steps,
+            window: window,
+        }
+    }
+
+    /// Whether the combo was completed at `now`, i.e. its last step was just
+    /// pressed. It is recognized during a single frame.
+    pub fn matches(&self, history: &InputHistory<T>, now: f64) -> bool {
+        let n = self.steps.len();
+        if n == 0 || history.presses.len() < n {
+            return false;
+        }
+
+        let last: Vec<&Press<T>> = history.presses.iter()
+            .skip(history.presses.len() - n)
+            .collect();
+        let first_time = last[0].time;
+        let last_time = last[n - 1].time;
+
+        last_time >= now &&
+            last_time - first_time <= self.window &&
+            last.iter().zip(self.steps.iter()).all(|(press, step)| press.input == *step)
+    }
+}
+
 // pub struct Events {
 //     pump: EventPump,
 // 
@@ -148,3 +899,77 @@ macro_rules! struct_events {
 //         }
 //     }
 // }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Combo, InputHistory, HISTORY_AGE};
+
+    #[test]
+    fn buffered_press_expires() {
+        let mut history = InputHistory::new();
+        history.push(1.0, 'a');
+        assert!(!history.take_buffered('a', 1.3, 0.2));
+        assert!(!history.take_buffered('b', 1.1, 0.2));
+        assert!(history.take_buffered('a', 1.1, 0.2));
+    }
+
+    #[test]
+    fn buffered_press_is_used_once() {
+        let mut history = InputHistory::new();
+        history.push(1.0, 'a');
+        history.push(1.1, 'a');
+
+        //? The most recent press goes first, then the older one if it is
+        //? still within the window.
+        assert!(history.take_buffered('a', 1.15, 0.1));
+        assert!(!history.take_buffered('a', 1.15, 0.1));
+        assert!(history.take_buffered('a', 1.15, 0.2));
+        assert!(!history.take_buffered('a', 1.15, 0.2));
+    }
+
+    #[test]
+    fn old_presses_are_forgotten() {
+        let mut history = InputHistory::new();
+        history.push(0.0, 'a');
+        history.push(HISTORY_AGE + 1.0, 'b');
+        assert_eq!(history.iter().map(|(_, input)| input).collect::<Vec<_>>(), vec!['b']);
+    }
+
+    #[test]
+    fn combo_in_order_within_its_window() {
+        let combo = Combo::new(vec!['u', 'u', 'd', 'd'], 1.0);
+        let mut history = InputHistory::new();
+        history.push(0.5, 'x');
+        for (i, &input) in ['u', 'u', 'd'].iter().enumerate() {
+            history.push(1.0 + i as f64 * 0.2, input);
+            assert!(!combo.matches(&history, 1.0 + i as f64 * 0.2));
+        }
+        history.push(1.6, 'd');
+        assert!(combo.matches(&history, 1.6));
+        //? It is only recognized during the frame of its last step.
+        assert!(!combo.matches(&history, 1.7));
+    }
+
+    #[test]
+    fn combo_out_of_order() {
+        let combo = Combo::new(vec!['u', 'u', 'd', 'd'], 1.0);
+        let mut history = InputHistory::new();
+        for (i, &input) in ['u', 'd', 'u', 'd'].iter().enumerate() {
+            history.push(i as f64 * 0.1, input);
+        }
+        assert!(!combo.matches(&history, 0.3));
+    }
+
+    #[test]
+    fn combo_slower_than_its_window() {
+        let combo = Combo::new(vec!['u', 'd'], 0.5);
+        let mut history = InputHistory::new();
+        history.push(0.0, 'u');
+        history.push(0.6, 'd');
+        assert!(!combo.matches(&history, 0.6));
+
+        //? An empty combo never matches.
+        assert!(!Combo::new(Vec::new(), 0.5).matches(&history, 0.6));
+    }
+}
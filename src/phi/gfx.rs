@@ -4,6 +4,7 @@ use ::std::cell::RefCell;
 use ::std::path::Path;
 use ::std::rc::Rc;
 
+use ::sdl2::pixels::Color;
 use ::sdl2::render::{Renderer, Texture};
 use ::sdl2_image::LoadTexture;
 
@@ -15,12 +16,17 @@ pub struct Sprite {
 
 pub trait CopySprite {
 	fn copy_sprite(&mut self, sprite: &Sprite, dest: Rectangle);
+	fn copy_sprite_tinted(&mut self, sprite: &Sprite, dest: Rectangle, color: Color);
 }
 
 impl<'window> CopySprite for Renderer<'window> {
 	fn copy_sprite(&mut self, sprite: &Sprite, dest: Rectangle) {
 		sprite.render(self, dest)
 	}
+
+	fn copy_sprite_tinted(&mut self, sprite: &Sprite, dest: Rectangle, color: Color) {
+		sprite.render_tinted(self, dest, color)
+	}
 }
 
 impl Sprite {
@@ -79,4 +85,27 @@ impl Sprite {
 			dest.to_sdl()
 		)
 	}
+
+	/// Render the bugger, multiplying its colours by `color`. Mostly useful
+	/// for white sprites, such as glyphs, which then take the given colour.
+	pub fn render_tinted(&self, renderer: &mut Renderer, dest: Rectangle, color: Color) {
+		let (r, g, b, a) = rgba(color);
+		let mut tex = self.tex.borrow_mut();
+
+		//? The texture may be shared with other sprites, so its modulation
+		//? is reset once we are done with it.
+		tex.set_color_mod(r, g, b);
+		tex.set_alpha_mod(a);
+		renderer.copy(&mut tex, self.src.to_sdl(), dest.to_sdl());
+		tex.set_color_mod(255, 255, 255);
+		tex.set_alpha_mod(255);
+	}
+}
+
+/// The channels of `color`, with an alpha of 255 for `Color::RGB`.
+pub fn rgba(color: Color) -> (u8, u8, u8, u8) {
+	match color {
+		Color::RGB(r, g, b) => (r, g, b, 255),
+		Color::RGBA(r, g, b, a) => (r, g, b, a),
+	}
 }
@@ -0,0 +1,243 @@
+use ::phi::{Events, Phi, View, ViewAction};
+use ::phi::capture::Frame;
+use ::phi::clock::{FrameClock, Pacing};
+use ::phi::config::Config;
+use ::phi::events;
+use ::phi::input::InputMap;
+use ::phi::rng::Rng;
+
+use ::std::fs;
+use ::std::path::Path;
+
+/// The duration of every frame of a golden run, whatever the time it takes.
+pub const TICK: f64 = 1.0 / 60.0;
+
+/// How far, in any channel, a pixel may be from the reference before it
+/// counts as different. Leaves room for the rounding of other renderers.
+pub const TOLERANCE: u8 = 8;
+
+/// The share of the pixels which may differ before a frame fails.
+pub const MAX_DIFFERING: f64 = 0.001;
+
+/// Where the reference frames are stored.
+pub const GOLDEN_DIR: &'static str = "assets/golden";
+
+/// A view run from a known state, whose frames are compared at `ticks` to
+/// `<name>_<tick>.png`.
+pub struct GoldenCase {
+	pub name: &'static str,
+	pub view: Box<Fn(&mut Phi) -> Box<View>>,
+	/// A replay, recorded with `--record`, which gives the input of every
+	/// frame. Without it, nothing is pressed and frames last `TICK`.
+	pub script: Option<&'static str>,
+	pub ticks: Vec<u32>,
+}
+
+impl GoldenCase {
+	pub fn new(name: &'static str, ticks: Vec<u32>, view: Box<Fn(&mut Phi) -> Box<View>>) -> GoldenCase {
+		GoldenCase {
+			name: name,
+			view: view,
+			script: None,
+			ticks: ticks,
+		}
+	}
+
+	pub fn scripted(mut self, script: &'static str) -> GoldenCase {
+		self.script = Some(script);
+		self
+	}
+}
+
+/// Runs every case in a hidden window with a software renderer, so that
+/// the result does not depend on the graphics card, and compares their
+/// frames to those in `dir`. A diff image, where the differing pixels are
+/// red, is written next to the reference of every frame which fails.
+///
+/// When `update` is set, the references are written instead.
+///
+/// Returns whether every frame matched its reference.
+pub fn run(cases: &[GoldenCase], dir: &str, update: bool) -> bool {
+	let sdl_context = ::sdl2::init().unwrap();
+	let video = sdl_context.video().unwrap();
+	let _ttf_context = ::sdl2_ttf::init();
+
+	//? The size of the default settings, so that the local settings file
+	//? does not change the frames.
+	let config = Config::default();
+	let (w, h) = config.resolution;
+	let window = video.window("ArcadeRS golden", w, h)
+		.hidden()
+		.build().unwrap();
+
+	let mut phi = Phi::new(
+		Events::new(
+			sdl_context.event_pump().unwrap(),
+			events::Gamepads::new(sdl_context.game_controller().unwrap()),
+			video.text_input(),
+		),
+		window.renderer().software().build().unwrap(),
+		config,
+		//? Frames last `TICK` whatever the clock says, so it need not wait.
+		FrameClock::new(sdl_context.timer().unwrap(), Pacing::Uncapped),
+	);
+	// Nor do the local bindings change how the scripts are read.
+	phi.input = InputMap::defaults();
+	phi.pause_unfocused = false;
+
+	if update {
+		if let Err(e) = fs::create_dir_all(dir) {
+			println!("Could not create {}: {}", dir, e);
+			return false;
+		}
+	}
+
+	let mut passed = true;
+	for case in cases {
+		passed = run_case(&mut phi, case, dir, update) && passed;
+	}
+	passed
+}
+
+fn run_case(phi: &mut Phi, case: &GoldenCase, dir: &str, update: bool) -> bool {
+	// Every case starts from the same state.
+	phi.stop_replay();
+	phi.events.release_all();
+	phi.events.history.clear();
+	phi.input.history.clear();
+	phi.rng = Rng::new(0);
+	phi.clock.paused = false;
+
+	if let Some(script) = case.script {
+		if !phi.play_replay(script) {
+			println!("{}: could not load the script {}", case.name, script);
+			return false;
+		}
+	}
+
+	let mut view = (case.view)(phi);
+	let last = case.ticks.iter().cloned().max().unwrap_or(0);
+	let mut passed = true;
+
+	for tick in 0..last + 1 {
+		phi.profiler.begin_frame();
+		phi.text_cache.begin_frame();
+		let elapsed = phi.pump_input(TICK);
+		::phi::tween::update(phi);
+
+		match view.render(phi, elapsed) {
+			ViewAction::None => {},
+			ViewAction::ChangeView(next) | ViewAction::Push(next) => {
+				//? Like `spawn`, the frame of a change is not presented, so
+				//? there is nothing to compare on that tick.
+				if case.ticks.contains(&tick) {
+					println!("{} @ {}: the view changed instead of rendering a frame", case.name, tick);
+					passed = false;
+				}
+				phi.clock.paused = false;
+				view = next;
+				continue;
+			},
+			ViewAction::Quit | ViewAction::Pop => {
+				println!("{}: the view left at tick {}", case.name, tick);
+				return false;
+			},
+		}
+
+		if case.ticks.contains(&tick) {
+			passed = check(phi, case.name, tick, dir, update) && passed;
+		}
+		phi.renderer.present();
+	}
+
+	passed
+}
+
+/// Compares the frame which was just rendered to its reference, or writes
+/// it as the reference.
+fn check(phi: &Phi, name: &str, tick: u32, dir: &str, update: bool) -> bool {
+	let mut frame = match Frame::read(&phi.renderer) {
+		Ok(frame) => frame,
+		Err(e) => {
+			println!("{} @ {}: could not read the frame back: {}", name, tick, e);
+			return false;
+		},
+	};
+
+	let path = Path::new(dir).join(format!("{}_{:04}.png", name, tick));
+
+	if update {
+		return match frame.save_png(&path) {
+			Ok(()) => {
+				println!("{} @ {}: wrote {}", name, tick, path.display());
+				true
+			},
+			Err(e) => {
+				println!("{} @ {}: could not write {}: {}", name, tick, path.display(), e);
+				false
+			},
+		};
+	}
+
+	let reference = match Frame::load_png(&path) {
+		Ok(reference) => reference,
+		Err(e) => {
+			println!("{} @ {}: could not load {} ({}), run with --golden-update to create it",
+				name, tick, path.display(), e);
+			return false;
+		},
+	};
+
+	if (reference.w, reference.h) != (frame.w, frame.h) {
+		println!("{} @ {}: the frame is {}x{}, but the reference is {}x{}",
+			name, tick, frame.w, frame.h, reference.w, reference.h);
+		return false;
+	}
+
+	let (differing, mut diff) = compare(&frame, &reference);
+	let share = differing as f64 / (frame.w * frame.h) as f64;
+
+	if share <= MAX_DIFFERING {
+		println!("{} @ {}: ok", name, tick);
+		return true;
+	}
+
+	let diff_path = Path::new(dir).join(format!("{}_{:04}.diff.png", name, tick));
+	match diff.save_png(&diff_path) {
+		Ok(()) => println!("{} @ {}: {} pixels differ, see {}", name, tick, differing, diff_path.display()),
+		Err(e) => println!("{} @ {}: {} pixels differ, and the diff could not be written: {}",
+			name, tick, differing, e),
+	}
+	false
+}
+
+/// Counts the pixels of `frame` which are further than `TOLERANCE` from
+/// `reference`. Also returns the diff image: those pixels in red, over a
+/// darkened copy of the frame.
+fn compare(frame: &Frame, reference: &Frame) -> (u32, Frame) {
+	let mut diff = Frame {
+		w: frame.w,
+		h: frame.h,
+		pixels: vec![0; frame.pixels.len()],
+	};
+	let mut differing = 0;
+
+	for y in 0..frame.h {
+		for x in 0..frame.w {
+			let (r, g, b, a) = frame.pixel(x, y);
+			let (rr, rg, rb, ra) = reference.pixel(x, y);
+			let distance = [(r, rr), (g, rg), (b, rb), (a, ra)].iter()
+				.map(|&(c, rc)| if c > rc { c - rc } else { rc - c })
+				.max().unwrap();
+
+			if distance > TOLERANCE {
+				differing += 1;
+				diff.set_pixel(x, y, (255, 0, 0, 255));
+			} else {
+				diff.set_pixel(x, y, (r / 4, g / 4, b / 4, 255));
+			}
+		}
+	}
+
+	(differing, diff)
+}
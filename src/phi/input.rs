@@ -0,0 +1,546 @@
+use ::phi::Events;
+use ::phi::events::{Combo, InputHistory};
+
+use ::std::collections::hash_map::HashMap;
+use ::std::collections::HashSet;
+use ::std::fs::File;
+use ::std::io::{self, Read, Write};
+
+use ::sdl2::controller::{Axis, Button};
+use ::sdl2::keyboard::Keycode;
+
+/// Where the bindings chosen by the player are stored.
+pub const BINDINGS_PATH: &'static str = "controls.cfg";
+
+/// What the player wants to do, independently of the input used to do it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+	MoveUp,
+	MoveDown,
+	MoveLeft,
+	MoveRight,
+	Fire,
+	Confirm,
+	Back,
+}
+
+/// Every action, in the order in which they are listed to the player.
+pub const ACTIONS: [Action; 7] = [
+	Action::MoveUp,
+	Action::MoveDown,
+	Action::MoveLeft,
+	Action::MoveRight,
+	Action::Fire,
+	Action::Confirm,
+	Action::Back,
+];
+
+impl Action {
+	/// The identifier of the action in the bindings file.
+	pub fn name(self) -> &'static str {
+		match self {
+			Action::MoveUp => "move_up",
+			Action::MoveDown => "move_down",
+			Action::MoveLeft => "move_left",
+			Action::MoveRight => "move_right",
+			Action::Fire => "fire",
+			Action::Confirm => "confirm",
+			Action::Back => "back",
+		}
+	}
+
+	/// The name of the action, as shown to the player.
+	pub fn label(self) -> &'static str {
+		match self {
+			Action::MoveUp => "Move Up",
+			Action::MoveDown => "Move Down",
+			Action::MoveLeft => "Move Left",
+			Action::MoveRight => "Move Right",
+			Action::Fire => "Fire",
+			Action::Confirm => "Confirm",
+			Action::Back => "Back",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Action> {
+		ACTIONS.iter().cloned().find(|action| action.name() == name)
+	}
+
+	/// Whether both actions can be used at the same time, in which case they
+	/// cannot share an input. `Fire` is only used while playing, and
+	/// `Confirm` and `Back` only in menus, so Space may trigger both `Fire`
+	/// and `Confirm`.
+	pub fn shares_context(self, other: Action) -> bool {
+		match (self, other) {
+			(Action::Fire, Action::Confirm) | (Action::Confirm, Action::Fire) |
+			(Action::Fire, Action::Back) | (Action::Back, Action::Fire) => false,
+			_ => true,
+		}
+	}
+}
+
+/// How far a stick must be pushed for an `Axis` binding to be held.
+const AXIS_THRESHOLD: f64 = 0.5;
+
+/// How an action repeats while it is held, like a key in a text editor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Repeat {
+	/// Seconds between the press and the first repetition.
+	pub delay: f64,
+	/// Repetitions per second after that.
+	pub rate: f64,
+}
+
+impl Repeat {
+	/// The repetition used for navigating menus.
+	pub fn navigation() -> Repeat {
+		Repeat {
+			delay: 0.4,
+			rate: 12.0,
+		}
+	}
+}
+
+/// A physical input which can trigger an action.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Binding {
+	Key(Keycode),
+	Button(Button),
+	/// An analog axis pushed past `AXIS_THRESHOLD`, towards its positive
+	/// end (`true`) or its negative end (`false`).
+	Axis(Axis, bool),
+}
+
+impl Binding {
+	/// The text used to store the binding, which is also shown to the player.
+	pub fn name(&self) -> String {
+		match *self {
+			Binding::Key(key) => key.name(),
+			Binding::Button(button) => format!("Pad {}", button.string()),
+			Binding::Axis(axis, positive) =>
+				format!("Axis {}{}", axis.string(), if positive { "+" } else { "-" }),
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Binding> {
+		if name.starts_with("Pad ") {
+			Button::from_string(&name["Pad ".len()..]).map(Binding::Button)
+		} else if name.starts_with("Axis ") && name.len() > "Axis ".len() + 1 {
+			let (axis, sign) = name["Axis ".len()..].split_at(name.len() - "Axis ".len() - 1);
+			Axis::from_string(axis).map(|axis| Binding::Axis(axis, sign == "+"))
+		} else {
+			Keycode::from_name(name).map(Binding::Key)
+		}
+	}
+
+	/// Whether both bindings come from the same kind of input: the keyboard,
+	/// the buttons of a controller, or its sticks.
+	pub fn same_kind(&self, other: &Binding) -> bool {
+		match (*self, *other) {
+			(Binding::Key(_), Binding::Key(_)) |
+			(Binding::Button(_), Binding::Button(_)) |
+			(Binding::Axis(..), Binding::Axis(..)) => true,
+			_ => false,
+		}
+	}
+
+	fn is_held(&self, events: &Events, player: usize) -> bool {
+		match *self {
+			Binding::Key(key) => events.is_held(key),
+			Binding::Button(button) => events.gamepads.button_held(player, button),
+			Binding::Axis(axis, positive) => {
+				let value = events.gamepads.axis(player, axis);
+				if positive { value > AXIS_THRESHOLD } else { value < -AXIS_THRESHOLD }
+			},
+		}
+	}
+}
+
+/// Maps physical inputs to actions. Several inputs may be bound to the same
+/// action, in which case it is held as long as any of them is.
+///
+/// Actions follow the same model as `Events`: `held` tells whether an action
+/// is currently active, and `now` whether it was just pressed (`Some(true)`),
+/// just released (`Some(false)`) or neither (`None`) during this frame.
+///
+/// Controller bindings only respond to the controller of `player`.
+///
+/// Actions which have a `Repeat` are also `repeated` while held, which is
+/// what scrolling through a menu needs.
+pub struct InputMap {
+	pub player: usize,
+	bindings: HashMap<Action, Vec<Binding>>,
+	repeats: HashMap<Action, Repeat>,
+	held: HashMap<Action, bool>,
+	now: HashMap<Action, Option<bool>>,
+	/// When each held action was pressed, according to `Events::time`.
+	pressed_at: HashMap<Action, f64>,
+	/// When each held action with a `Repeat` should next repeat.
+	next_repeat: HashMap<Action, f64>,
+	repeated: HashMap<Action, bool>,
+	/// The actions pressed recently, for combos and buffering.
+	pub history: InputHistory<Action>,
+	time: f64,
+	/// Whether every action is reported as released; see `set_suspended`.
+	suspended: bool,
+	/// The actions whose inputs were held while suspended, which stay
+	/// released until those inputs are.
+	ignored: HashSet<Action>,
+}
+
+impl InputMap {
+	/// The bindings used when none were saved, or when they are reset.
+	pub fn defaults() -> InputMap {
+		use ::sdl2::keyboard::Keycode::*;
+
+		let mut map = InputMap {
+			player: 0,
+			bindings: HashMap::new(),
+			repeats: HashMap::new(),
+			held: HashMap::new(),
+			now: HashMap::new(),
+			pressed_at: HashMap::new(),
+			next_repeat: HashMap::new(),
+			repeated: HashMap::new(),
+			history: InputHistory::new(),
+			time: 0.0,
+			suspended: false,
+			ignored: HashSet::new(),
+		};
+
+		for action in &[Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight] {
+			map.repeats.insert(*action, Repeat::navigation());
+		}
+
+		map.bindings.insert(Action::MoveUp, vec![
+			Binding::Key(Up), Binding::Key(W),
+			Binding::Button(Button::DPadUp), Binding::Axis(Axis::LeftY, false),
+		]);
+		map.bindings.insert(Action::MoveDown, vec![
+			Binding::Key(Down), Binding::Key(S),
+			Binding::Button(Button::DPadDown), Binding::Axis(Axis::LeftY, true),
+		]);
+		map.bindings.insert(Action::MoveLeft, vec![
+			Binding::Key(Left), Binding::Key(A),
+			Binding::Button(Button::DPadLeft), Binding::Axis(Axis::LeftX, false),
+		]);
+		map.bindings.insert(Action::MoveRight, vec![
+			Binding::Key(Right), Binding::Key(D),
+			Binding::Button(Button::DPadRight), Binding::Axis(Axis::LeftX, true),
+		]);
+		map.bindings.insert(Action::Fire, vec![Binding::Key(Space), Binding::Button(Button::A)]);
+		map.bindings.insert(Action::Confirm, vec![
+			Binding::Key(Return), Binding::Key(Space), Binding::Button(Button::A),
+		]);
+		map.bindings.insert(Action::Back, vec![Binding::Key(Escape), Binding::Button(Button::B)]);
+
+		map
+	}
+
+	/// Loads the bindings stored at `path`. Actions which are missing from
+	/// the file keep their default bindings, and so does everything if the
+	/// file cannot be read.
+	///
+	/// The file holds one action per line, followed by the names of the
+	/// inputs bound to it:
+	///
+	/// ```text
+	/// move_up = Up, W
+	/// fire = Space
+	/// ```
+	pub fn load(path: &str) -> InputMap {
+		let mut map = InputMap::defaults();
+
+		let mut source = String::new();
+		if File::open(path).and_then(|mut file| file.read_to_string(&mut source)).is_err() {
+			return map;
+		}
+
+		for line in source.lines() {
+			let mut parts = line.splitn(2, '=');
+			let (name, inputs) = match (parts.next(), parts.next()) {
+				(Some(name), Some(inputs)) => (name.trim(), inputs),
+				_ => continue,
+			};
+
+			if let Some(action) = Action::from_name(name) {
+				let bindings: Vec<Binding> = inputs.split(',')
+					.map(|input| input.trim())
+					.filter(|input| !input.is_empty())
+					.filter_map(Binding::from_name)
+					.collect();
+				//? An empty or malformed line keeps the defaults, rather than
+				//? leaving the action without any input.
+				if !bindings.is_empty() {
+					map.bindings.insert(action, bindings);
+				}
+			}
+		}
+
+		//? A hand-edited file may still take away every key of the menus,
+		//? which nothing in the game could give back.
+		if !map.can_use(Action::Confirm) || !map.can_use(Action::Back) {
+			println!("Ignoring {}, which leaves the menus without Confirm or Back", path);
+			return InputMap::defaults();
+		}
+
+		map
+	}
+
+	/// Whether `action` has a key of its own: one which no action used at
+	/// the same time is also bound to.
+	fn can_use(&self, action: Action) -> bool {
+		self.bindings(action).iter().any(|binding| match *binding {
+			Binding::Key(_) => ACTIONS.iter()
+				.filter(|&&other| other != action && other.shares_context(action))
+				.all(|&other| !self.bindings(other).contains(binding)),
+			_ => false,
+		})
+	}
+
+	pub fn save(&self, path: &str) -> io::Result<()> {
+		let mut file = try!(File::create(path));
+
+		for action in ACTIONS.iter() {
+			let names: Vec<String> = self.bindings(*action).iter()
+				.map(|binding| binding.name())
+				.collect();
+			try!(writeln!(file, "{} = {}", action.name(), names.join(", ")));
+		}
+
+		Ok(())
+	}
+
+	pub fn bindings(&self, action: Action) -> &[Binding] {
+		self.bindings.get(&action).map(|bindings| &bindings[..]).unwrap_or(&[])
+	}
+
+	/// Whether `binding` can be bound to `action`. It cannot when it would
+	/// be taken from the last input of its kind bound to `Confirm` or
+	/// `Back`, which would leave the player unable to use the menus.
+	pub fn can_bind(&self, action: Action, binding: Binding) -> bool {
+		[Action::Confirm, Action::Back].iter().all(|&needed| {
+			let bindings = self.bindings(needed);
+			needed == action || !needed.shares_context(action) ||
+				!bindings.contains(&binding) ||
+				bindings.iter().filter(|other| other.same_kind(&binding)).count() > 1
+		})
+	}
+
+	/// Binds `binding` to `action`, in addition to its current bindings. The
+	/// input is removed from the actions which can be used at the same time
+	/// as `action`, so that one key never triggers two actions at once.
+	///
+	/// Returns `false`, without changing anything, if `can_bind` refuses it.
+	pub fn bind(&mut self, action: Action, binding: Binding) -> bool {
+		if !self.can_bind(action, binding) {
+			return false;
+		}
+
+		for (other, bindings) in self.bindings.iter_mut() {
+			if other.shares_context(action) {
+				bindings.retain(|other| *other != binding);
+			}
+		}
+		self.bindings.entry(action).or_insert(Vec::new()).push(binding);
+		true
+	}
+
+	/// Removes every input of the same kind as `like` bound to `action`:
+	/// rebinding the keyboard should not unbind the controller.
+	pub fn unbind_like(&mut self, action: Action, like: Binding) {
+		if let Some(bindings) = self.bindings.get_mut(&action) {
+			bindings.retain(|binding| !binding.same_kind(&like));
+		}
+	}
+
+	/// Sets how `action` repeats while held, or stops it from repeating.
+	/// Repetitions are not saved with the bindings.
+	pub fn set_repeat(&mut self, action: Action, repeat: Option<Repeat>) {
+		match repeat {
+			Some(repeat) => { self.repeats.insert(action, repeat); },
+			None => { self.repeats.remove(&action); },
+		}
+	}
+
+	/// Recomputes the state of every action from the state of the inputs.
+	/// Called by `spawn` after the events were pumped.
+	pub fn update(&mut self, events: &Events) {
+		self.time = events.time;
+
+		for action in ACTIONS.iter() {
+			let was_held = self.held(*action);
+			let pressed = self.bindings(*action).iter()
+				.any(|binding| binding.is_held(events, self.player));
+
+			if self.suspended && pressed {
+				self.ignored.insert(*action);
+			} else if !pressed {
+				self.ignored.remove(action);
+			}
+			let is_held = pressed && !self.suspended && !self.ignored.contains(action);
+
+			self.now.insert(*action,
+				if is_held == was_held { None }
+				else { Some(is_held) });
+			self.held.insert(*action, is_held);
+			if is_held && !was_held {
+				self.history.push(self.time, *action);
+			}
+
+			let repeated = self.update_repeat(*action, was_held, is_held);
+			self.repeated.insert(*action, repeated);
+		}
+	}
+
+	/// Whether `action` fires this frame, either because it was pressed or
+	/// because it is repeated.
+	fn update_repeat(&mut self, action: Action, was_held: bool, is_held: bool) -> bool {
+		if !is_held {
+			self.pressed_at.remove(&action);
+			self.next_repeat.remove(&action);
+			return false;
+		}
+
+		let repeat = self.repeats.get(&action).cloned();
+
+		if !was_held {
+			self.pressed_at.insert(action, self.time);
+			if let Some(repeat) = repeat {
+				self.next_repeat.insert(action, self.time + repeat.delay);
+			}
+			return true;
+		}
+
+		match (repeat, self.next_repeat.get(&action).cloned()) {
+			(Some(repeat), Some(next)) if next <= self.time => {
+				//? After a long frame, repeat once rather than catching up
+				//? with every repetition which was missed.
+				let interval = 1.0 / repeat.rate;
+				let next =
+					if next + interval > self.time { next + interval }
+					else { self.time + interval };
+				self.next_repeat.insert(action, next);
+				true
+			},
+			_ => false,
+		}
+	}
+
+	pub fn held(&self, action: Action) -> bool {
+		self.held.get(&action).cloned().unwrap_or(false)
+	}
+
+	pub fn now(&self, action: Action) -> Option<bool> {
+		self.now.get(&action).cloned().unwrap_or(None)
+	}
+
+	/// While suspended, e.g. when the keyboard is used by the console rather
+	/// than by the game, every action is released. Inputs which are held
+	/// when it resumes are ignored until they are released, so that the
+	/// keys typed in the console do not reach the game.
+	pub fn set_suspended(&mut self, suspended: bool) {
+		self.suspended = suspended;
+	}
+
+	/// Forgets what was pressed and released during this frame, e.g. when
+	/// the keyboard is used by the console rather than by the game.
+	pub fn clear_frame(&mut self) {
+		self.now.clear();
+		self.repeated.clear();
+	}
+
+	/// Whether `action` was just pressed, which is what menus react to.
+	pub fn pressed(&self, action: Action) -> bool {
+		self.now(action) == Some(true)
+	}
+
+	/// Whether `action` was just pressed, or is held and repeats during this
+	/// frame. Actions without a `Repeat` behave like `pressed`.
+	pub fn repeated(&self, action: Action) -> bool {
+		self.repeated.get(&action).cloned().unwrap_or(false)
+	}
+
+	/// Whether `action` was pressed during the last `window` seconds and not
+	/// used yet, in which case it is used up; see `InputHistory::take_buffered`.
+	pub fn take_buffered(&mut self, action: Action, window: f64) -> bool {
+		let now = self.time;
+		self.history.take_buffered(action, now, window)
+	}
+
+	/// Whether `combo` was just completed with actions.
+	pub fn combo(&self, combo: &Combo<Action>) -> bool {
+		combo.matches(&self.history, self.time)
+	}
+
+	/// For how many seconds `action` has been held, or 0 if it is not; e.g.
+	/// to charge a shot while Fire is held.
+	pub fn held_for(&self, action: Action) -> f64 {
+		self.pressed_at.get(&action).map(|at| self.time - at).unwrap_or(0.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Action, Binding, InputMap};
+
+	use ::std::fs::File;
+	use ::std::io::Write;
+
+	use ::sdl2::controller::Button;
+	use ::sdl2::keyboard::Keycode;
+
+	/// Writes `contents` to a file of the temporary directory and loads it.
+	fn load(name: &str, contents: &str) -> InputMap {
+		let path = ::std::env::temp_dir().join(name);
+		File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+		InputMap::load(&path.to_string_lossy())
+	}
+
+	#[test]
+	fn load_keeps_the_defaults_of_empty_lines() {
+		let map = load("arcade-rs-test-controls-empty.cfg", "back =\nconfirm = , \n");
+		let defaults = InputMap::defaults();
+		assert_eq!(map.bindings(Action::Back), defaults.bindings(Action::Back));
+		assert_eq!(map.bindings(Action::Confirm), defaults.bindings(Action::Confirm));
+	}
+
+	#[test]
+	fn menus_need_a_key_of_their_own() {
+		let mut map = InputMap::defaults();
+		assert!(map.can_use(Action::Confirm) && map.can_use(Action::Back));
+
+		//? Escape also moves up, so Back has no key of its own.
+		map.bindings.insert(Action::MoveUp, vec![Binding::Key(Keycode::Escape)]);
+		assert!(!map.can_use(Action::Back));
+
+		//? Fire is never used at the same time as Back.
+		map.bindings.insert(Action::MoveUp, vec![Binding::Key(Keycode::Up)]);
+		map.bindings.insert(Action::Fire, vec![Binding::Key(Keycode::Escape)]);
+		assert!(map.can_use(Action::Back));
+
+		map.bindings.insert(Action::Back, vec![Binding::Button(Button::B)]);
+		assert!(!map.can_use(Action::Back));
+	}
+
+	#[test]
+	fn bind_keeps_inputs_of_other_contexts() {
+		let mut map = InputMap::defaults();
+		assert!(map.bind(Action::Fire, Binding::Key(Keycode::Return)));
+		assert!(map.bindings(Action::Confirm).contains(&Binding::Key(Keycode::Return)));
+		assert!(!map.bindings(Action::MoveUp).contains(&Binding::Key(Keycode::Return)));
+	}
+
+	#[test]
+	fn bind_refuses_the_last_key_of_back() {
+		let mut map = InputMap::defaults();
+		assert!(!map.bind(Action::MoveUp, Binding::Key(Keycode::Escape)));
+		assert!(map.bindings(Action::Back).contains(&Binding::Key(Keycode::Escape)));
+		assert!(!map.bindings(Action::MoveUp).contains(&Binding::Key(Keycode::Escape)));
+
+		// Confirm is also bound to Space, so it can lose Return.
+		assert!(map.bind(Action::MoveUp, Binding::Key(Keycode::Return)));
+		assert_eq!(map.bindings(Action::Confirm),
+			&[Binding::Key(Keycode::Space), Binding::Button(Button::A)]);
+	}
+}
@@ -0,0 +1,385 @@
+use ::phi::data::Rectangle;
+use ::phi::gfx::{CopySprite, Sprite};
+use ::phi::text::{Align, AtlasCache};
+
+use ::sdl2::pixels::Color;
+use ::sdl2::render::Renderer;
+use ::sdl2_ttf::FontStyle;
+
+/// Animations which may be applied, character by character, to a span.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+	None,
+	/// Characters bob up and down, each one slightly after the previous.
+	Wave,
+	/// Characters jitter around their position.
+	Shake,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SpanStyle {
+	/// `None` means that the colour given when drawing is used.
+	pub color: Option<Color>,
+	pub bold: bool,
+	pub italic: bool,
+	pub underline: bool,
+	pub effect: Effect,
+}
+
+impl SpanStyle {
+	pub fn plain() -> SpanStyle {
+		SpanStyle {
+			color: None,
+			bold: false,
+			italic: false,
+			underline: false,
+			effect: Effect::None,
+		}
+	}
+
+	/// The SDL_ttf style with which the characters of the span are rendered.
+	pub fn font_style(&self) -> FontStyle {
+		let mut style = ::sdl2_ttf::STYLE_NORMAL;
+		if self.bold { style = style | ::sdl2_ttf::STYLE_BOLD; }
+		if self.italic { style = style | ::sdl2_ttf::STYLE_ITALIC; }
+		if self.underline { style = style | ::sdl2_ttf::STYLE_UNDERLINE; }
+		style
+	}
+}
+
+/// A run of text sharing the same style.
+#[derive(Clone, Debug)]
+pub struct Span {
+	pub text: String,
+	pub style: SpanStyle,
+}
+
+/// Parses `markup` into styled spans.
+///
+/// Tags are enclosed in square brackets and closed by the same name prefixed
+/// with a slash: `[b]`, `[i]`, `[u]`, `[wave]`, `[shake]` and
+/// `[color=#rgb]` (or `#rrggbb`, `#rrggbbaa`). Tags may be nested; a closing
+/// tag ends the innermost open tag with that name. `[[` produces a literal
+/// `[`, and tags which cannot be parsed are kept as plain text.
+///
+/// ```
+/// parse("Press [b][color=#ff0]Space[/color][/b] to [wave]start[/wave]");
+/// ```
+pub fn parse(markup: &str) -> Vec<Span> {
+	let mut spans: Vec<Span> = Vec::new();
+	//? Every open tag, with the style which was active before it opened.
+	let mut stack: Vec<(String, SpanStyle)> = Vec::new();
+	let mut style = SpanStyle::plain();
+	let mut text = String::new();
+	let mut rest = markup;
+
+	while !rest.is_empty() {
+		if rest.starts_with("[[") {
+			text.push('[');
+			rest = &rest[2..];
+			continue;
+		}
+
+		if rest.starts_with('[') {
+			if let Some(end) = rest.find(']') {
+				let tag = &rest[1..end];
+
+				let parsed =
+					if tag.starts_with('/') { close_tag(&tag[1..], &mut stack) }
+					else { open_tag(tag, &style).map(|new_style| (tag, new_style)) };
+
+				if let Some((name, new_style)) = parsed {
+					flush(&mut spans, &mut text, style);
+					if !tag.starts_with('/') {
+						let name = name.split('=').next().unwrap_or(name).to_string();
+						stack.push((name, style));
+					}
+					style = new_style;
+					rest = &rest[end + 1..];
+					continue;
+				}
+			}
+		}
+
+		//? Not a tag: copy the next character as-is.
+		let ch = rest.chars().next().unwrap();
+		text.push(ch);
+		rest = &rest[ch.len_utf8()..];
+	}
+
+	flush(&mut spans, &mut text, style);
+	spans
+}
+
+/// Ends the current span, if it holds any text.
+fn flush(spans: &mut Vec<Span>, text: &mut String, style: SpanStyle) {
+	if !text.is_empty() {
+		spans.push(Span {
+			text: text.clone(),
+			style: style,
+		});
+		text.clear();
+	}
+}
+
+/// Returns the style in effect inside of `tag`, or `None` if it is invalid.
+fn open_tag(tag: &str, style: &SpanStyle) -> Option<SpanStyle> {
+	let mut style = *style;
+
+	match tag {
+		"b" => style.bold = true,
+		"i" => style.italic = true,
+		"u" => style.underline = true,
+		"wave" => style.effect = Effect::Wave,
+		"shake" => style.effect = Effect::Shake,
+		_ if tag.starts_with("color=") => {
+			match parse_color(&tag["color=".len()..]) {
+				Some(color) => style.color = Some(color),
+				None => return None,
+			}
+		},
+		_ => return None,
+	}
+
+	Some(style)
+}
+
+/// Pops the innermost tag named `name`, along with every tag opened after it,
+/// and returns the style which was active before it.
+fn close_tag<'a>(name: &'a str, stack: &mut Vec<(String, SpanStyle)>) -> Option<(&'a str, SpanStyle)> {
+	match stack.iter().rposition(|&(ref open, _)| open == name) {
+		Some(index) => {
+			let (_, style) = stack[index].clone();
+			stack.truncate(index);
+			Some((name, style))
+		},
+		None => None,
+	}
+}
+
+/// Parses `#rgb`, `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(hex: &str) -> Option<Color> {
+	if !hex.starts_with('#') {
+		return None;
+	}
+
+	let digits: Vec<u8> = match hex[1..].chars()
+		.map(|ch| ch.to_digit(16).map(|d| d as u8))
+		.collect() {
+		Some(digits) => digits,
+		None => return None,
+	};
+
+	match digits.len() {
+		//? #rgb is a shorthand for #rrggbb: every digit is repeated.
+		3 => Some(Color::RGB(digits[0] * 17, digits[1] * 17, digits[2] * 17)),
+		6 => Some(Color::RGB(
+			digits[0] * 16 + digits[1],
+			digits[2] * 16 + digits[3],
+			digits[4] * 16 + digits[5])),
+		8 => Some(Color::RGBA(
+			digits[0] * 16 + digits[1],
+			digits[2] * 16 + digits[3],
+			digits[4] * 16 + digits[5],
+			digits[6] * 16 + digits[7])),
+		_ => None,
+	}
+}
+
+
+/// A glyph of a `RichLayout`, positioned relative to its top-left corner.
+#[derive(Clone)]
+pub struct RichGlyph {
+	pub sprite: Sprite,
+	pub dest: Rectangle,
+	pub color: Option<Color>,
+	pub effect: Effect,
+	/// The position of the character in the text, used to offset animations.
+	pub index: usize,
+}
+
+/// Styled text which was positioned glyph by glyph. Lines are broken on
+/// `\n` and aligned relative to the longest one.
+#[derive(Clone)]
+pub struct RichLayout {
+	pub glyphs: Vec<RichGlyph>,
+	pub w: f64,
+	pub h: f64,
+}
+
+impl RichLayout {
+	pub fn new(atlases: &mut AtlasCache, renderer: &Renderer, font_path: &'static str,
+	           size: i32, spans: &[Span], align: Align) -> RichLayout {
+		let mut glyphs = Vec::new();
+		//? The index of the first glyph and the width of every line.
+		let mut lines: Vec<(usize, f64)> = vec![(0, 0.0)];
+		let mut line_height: f64 = 0.0;
+		let mut pen_x = 0.0;
+		let mut index = 0;
+
+		//? Rasterize every glyph before the first one is requested, so that
+		//? each atlas uploads its pages once rather than once per new glyph.
+		for span in spans {
+			if let Some(atlas) = atlases.get(font_path, size, span.style.font_style()) {
+				atlas.prepare(&span.text);
+			}
+		}
+
+		for span in spans {
+			let atlas = match atlases.get(font_path, size, span.style.font_style()) {
+				Some(atlas) => atlas,
+				None => continue,
+			};
+			line_height = line_height.max(atlas.line_height());
+			let mut prev = None;
+
+			for ch in span.text.chars() {
+				index += 1;
+
+				if ch == '\n' {
+					lines.push((glyphs.len(), 0.0));
+					pen_x = 0.0;
+					prev = None;
+					continue;
+				}
+
+				let sprite = match atlas.glyph_sprite(renderer, ch) {
+					Some(sprite) => sprite,
+					None => continue,
+				};
+				if let Some(prev) = prev {
+					pen_x += atlas.kerning(prev, ch);
+				}
+
+				let (w, h) = sprite.size();
+				glyphs.push(RichGlyph {
+					sprite: sprite,
+					dest: Rectangle {
+						x: pen_x,
+						y: (lines.len() - 1) as f64,
+						w: w,
+						h: h,
+					},
+					color: span.style.color,
+					effect: span.style.effect,
+					index: index,
+				});
+
+				pen_x += atlas.advance(ch).unwrap_or(w);
+				prev = Some(ch);
+				lines.last_mut().unwrap().1 = pen_x;
+			}
+		}
+
+		//? Now that the widest line is known, we can align every line and
+		//? turn line numbers into actual heights.
+		let widest = lines.iter().map(|line| line.1).fold(0.0, f64::max);
+		for (row, &(start, width)) in lines.iter().enumerate() {
+			let end = lines.get(row + 1).map(|line| line.0).unwrap_or(glyphs.len());
+			let offset = match align {
+				Align::Left => 0.0,
+				Align::Center => (widest - width) / 2.0,
+				Align::Right => widest - width,
+			};
+
+			for glyph in &mut glyphs[start..end] {
+				glyph.dest.x += offset;
+				glyph.dest.y *= line_height;
+			}
+		}
+
+		RichLayout {
+			glyphs: glyphs,
+			w: widest,
+			h: lines.len() as f64 * line_height,
+		}
+	}
+
+	pub fn size(&self) -> (f64, f64) {
+		(self.w, self.h)
+	}
+}
+
+pub trait CopyRichText {
+	/// Draws `layout` with its top-left corner at `(x, y)`. Spans without a
+	/// colour of their own are drawn with `color`. `time`, in seconds, drives
+	/// the animated effects.
+	fn copy_rich_text(&mut self, layout: &RichLayout, x: f64, y: f64, color: Color, time: f64);
+}
+
+impl<'window> CopyRichText for Renderer<'window> {
+	fn copy_rich_text(&mut self, layout: &RichLayout, x: f64, y: f64, color: Color, time: f64) {
+		for glyph in &layout.glyphs {
+			let (dx, dy) = effect_offset(glyph.effect, glyph.index, time, glyph.dest.h);
+
+			self.copy_sprite_tinted(&glyph.sprite, Rectangle {
+				x: x + glyph.dest.x + dx,
+				y: y + glyph.dest.y + dy,
+				.. glyph.dest
+			}, glyph.color.unwrap_or(color));
+		}
+	}
+}
+
+/// How far the `index`-th character is moved by `effect` at `time`.
+fn effect_offset(effect: Effect, index: usize, time: f64, height: f64) -> (f64, f64) {
+	match effect {
+		Effect::None => (0.0, 0.0),
+		Effect::Wave => (0.0, (time * 6.0 - index as f64 * 0.6).sin() * height * 0.12),
+		Effect::Shake => {
+			//? A cheap hash of the character and of the current "shake step",
+			//? so that every character jumps to a new position 30 times per
+			//? second, independently of the frame rate.
+			let step = (time * 30.0) as u64;
+			let seed = (index as u64).wrapping_mul(2654435761) ^ step.wrapping_mul(40503);
+			let amplitude = height * 0.06;
+			let dx = ((seed % 1000) as f64 / 500.0 - 1.0) * amplitude;
+			let dy = (((seed / 1000) % 1000) as f64 / 500.0 - 1.0) * amplitude;
+			(dx, dy)
+		},
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{parse, parse_color, Effect};
+	use ::phi::gfx::rgba;
+
+	#[test]
+	fn parse_nested_tags() {
+		let spans = parse("Press [b][color=#ff0]Space[/color][/b] to [wave]start[/wave]");
+		let texts: Vec<&str> = spans.iter().map(|span| &span.text[..]).collect();
+		assert_eq!(texts, vec!["Press ", "Space", " to ", "start"]);
+
+		assert!(!spans[0].style.bold && spans[0].style.color.is_none());
+		assert!(spans[1].style.bold);
+		assert_eq!(spans[1].style.color.map(rgba), Some((255, 255, 0, 255)));
+		assert!(!spans[2].style.bold && spans[2].style.color.is_none());
+		assert_eq!(spans[3].style.effect, Effect::Wave);
+	}
+
+	#[test]
+	fn parse_closes_tags_opened_after_the_closed_one() {
+		let spans = parse("[b]a[i]b[/b]c");
+		assert_eq!(spans.len(), 3);
+		assert!(spans[1].style.bold && spans[1].style.italic);
+		assert!(!spans[2].style.bold && !spans[2].style.italic);
+	}
+
+	#[test]
+	fn parse_keeps_invalid_tags_as_text() {
+		let spans = parse("[[b] [nope] [color=#12] [/i]");
+		assert_eq!(spans.len(), 1);
+		assert_eq!(spans[0].text, "[b] [nope] [color=#12] [/i]");
+	}
+
+	#[test]
+	fn parse_colors() {
+		assert_eq!(parse_color("#f80").map(rgba), Some((255, 136, 0, 255)));
+		assert_eq!(parse_color("#102030").map(rgba), Some((16, 32, 48, 255)));
+		assert_eq!(parse_color("#10203040").map(rgba), Some((16, 32, 48, 64)));
+		assert!(parse_color("102030").is_none());
+		assert!(parse_color("#10203g").is_none());
+	}
+}
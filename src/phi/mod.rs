@@ -1,30 +1,66 @@
 #[macro_use]
-mod events;
+pub mod events;
+pub mod bmfont;
+pub mod capture;
+pub mod clock;
+pub mod config;
+pub mod console;
 pub mod data;
+pub mod debug;
 pub mod gfx;
+pub mod golden;
+pub mod input;
+pub mod markup;
+pub mod profiler;
+pub mod replay;
+pub mod rng;
+pub mod text;
+pub mod tween;
 
 use ::std::path::Path;
 use ::std::collections::hash_map::HashMap;
 
+use ::phi::bmfont::BitmapFont;
+use ::phi::capture::Capture;
+use ::phi::clock::{FrameClock, Pacing, PauseStack};
+use ::phi::config::Config;
+use ::phi::console::Console;
+use ::phi::debug::{DebugDraw, DebugUi};
 use ::phi::gfx::Sprite;
+use ::phi::input::InputMap;
+use ::phi::markup::RichLayout;
+use ::phi::profiler::Profiler;
+use ::phi::replay::{FrameInput, Recorder, Replay};
+use ::phi::rng::Rng;
+use ::phi::tween::Scheduler;
+use ::phi::text::{Align, AtlasCache, DigitAtlas, TextCache, TextLayout, TextOptions};
 
 use ::sdl2::render::Renderer;
 use ::sdl2::pixels::Color;
 
 // We cannot call functions at top-level.
 // However, `struct_events` is a macro!
+//
+// Keys are not aliased here: views read actions from `Phi::input`, which
+// maps them to whichever keys the player chose.
 struct_events!(
-    keyboard: {
-        key_escape: Escape,
-        key_up: Up,
-        key_down: Down,
-        key_left: Left,
-        key_right: Right,
-        key_space: Space,
-        key_return: Return
-    },
+    keyboard: {},
     else: {
         quit: Quit { .. }
+    },
+    queue: {
+        // Paths of the files dropped onto the window.
+        dropped_files: String = DropFile { filename, .. } => filename
+    },
+    held: {
+        focused: bool = true; {
+            Window { win_event_id: WindowEventId::FocusGained, .. } => true,
+            Window { win_event_id: WindowEventId::FocusLost, .. } => false
+        },
+        minimized: bool = false; {
+            Window { win_event_id: WindowEventId::Minimized, .. } => true,
+            Window { win_event_id: WindowEventId::Restored, .. } => false
+        }
     }
 );
 
@@ -32,18 +68,66 @@ struct_events!(
 /// can be passed around more easily.
 pub struct Phi<'a> {
     pub events: Events,
+    pub input: InputMap,
+    /// The settings of the game; call `apply_config` after changing them.
+    pub config: Config,
+    /// The source of every random number used by the game, so that replays
+    /// are deterministic.
+    pub rng: Rng,
+    /// Whether time stops while the window is unfocused or minimized: views
+    /// are still rendered, but with no time elapsing.
+    pub pause_unfocused: bool,
+    /// Measures and paces the frames.
+    pub clock: FrameClock,
+    /// Runs callbacks after some time, as the clock goes.
+    pub scheduler: Scheduler,
     pub renderer: Renderer<'a>,
+    /// The overlay which shows and edits variables, toggled with F1.
+    pub debug: DebugUi,
+    /// Shapes drawn over the frame for debugging, toggled with F2.
+    pub debug_draw: DebugDraw,
+    /// The developer console, opened with the key above Tab.
+    pub console: Console,
+    /// Writes screenshots, taken with F12, and sequences of frames.
+    pub capture: Capture,
+    /// Times the parts of every frame, and graphs them with F3.
+    pub profiler: Profiler,
+    /// Where the next game is recorded, from its first frame, so that the
+    /// replay can be played back as the demo of the main menu.
+    pub demo_recording: Option<String>,
 		cached_fonts: HashMap<(&'static str, i32), ::sdl2_ttf::Font>,
+		text_cache: TextCache,
+		atlases: AtlasCache,
+		bitmap_fonts: HashMap<&'static str, BitmapFont>,
+		recorder: Option<Recorder>,
+		playback: Option<Replay>,
 }
 
 impl<'window> Phi<'window> {
-    fn new(events: Events, renderer: Renderer<'window>) -> Phi<'window> {
+    fn new(events: Events, renderer: Renderer<'window>, config: Config, clock: FrameClock) -> Phi<'window> {
 			::sdl2_image::init(::sdl2_image::INIT_PNG);
 
 			Phi {
 				events: events,
+				input: InputMap::load(::phi::input::BINDINGS_PATH),
+				config: config,
+				rng: Rng::from_time(),
+				pause_unfocused: true,
+				clock: clock,
+				scheduler: Scheduler::new(),
 				renderer: renderer,
+				debug: DebugUi::new(),
+				debug_draw: DebugDraw::new(),
+				console: Console::new(),
+				capture: Capture::new(),
+				profiler: Profiler::new(),
+				demo_recording: None,
 				cached_fonts: HashMap::new(),
+				text_cache: TextCache::new(256, 32),
+				atlases: AtlasCache::new(),
+				bitmap_fonts: HashMap::new(),
+				recorder: None,
+				playback: None,
 			}
 		}
 
@@ -51,36 +135,200 @@ impl<'window> Phi<'window> {
         self.renderer.output_size().unwrap()
     }
 
-		pub fn ttf_str_sprite(&mut self, text: &str, font_path: &'static str, size: i32, color: Color) -> Option<Sprite> {
-			//? First, we verify whether the font is already cached. If this is the
-			//? case, we use it to render the text
-			if let Some(font) = self.cached_fonts.get(&(font_path, size)) {
-				return font.render(text, ::sdl2_ttf::blended(color)).ok()
-					.and_then(|s| self.renderer.create_texture_from_surface(&s).ok())
-					.map(Sprite::new)
+    /// Applies the window settings of `config`, except for the vsync which
+    /// only changes when the game is restarted.
+    pub fn apply_config(&mut self) {
+        use ::sdl2::video::FullscreenType;
+
+        let fullscreen = self.config.fullscreen;
+        let (w, h) = self.config.resolution;
+
+        if let Some(window) = self.renderer.window_mut() {
+            let mode = if fullscreen { FullscreenType::Desktop } else { FullscreenType::Off };
+            if let Err(e) = window.set_fullscreen(mode) {
+                println!("Could not change the fullscreen mode: {}", e);
+            }
+            if !fullscreen {
+                window.set_size(w, h);
+            }
+        }
+    }
+
+		/// Loads the font at `font_path` with the given `size`, unless it is
+		/// already cached. Returns whether the font is available.
+		fn load_font(&mut self, font_path: &'static str, size: i32) -> bool {
+			if self.cached_fonts.contains_key(&(font_path, size)) {
+				return true;
 			}
-			//? Start by trying to load the font
-			::sdl2_ttf::Font::from_file(Path::new(font_path), size).ok()
-				.and_then(|font| {
-					//? If this works, we cache the font we acquired
+
+			match ::sdl2_ttf::Font::from_file(Path::new(font_path), size) {
+				Ok(font) => {
 					self.cached_fonts.insert((font_path, size), font);
-					//? Then, we call the method recursively. Because we know that
-					//? the font has been cached, the `if` block will be executed
-					self.ttf_str_sprite(text, font_path, size, color)
-				})
-				//? Next steps must be wrapped in a closure because of the
-				//? borrow checker. `font` must live at least until the texture 
-				//? is created.
-				//? .and_then(|font| font
-					//? If this worked, we try to create a surface from the font.
-				//? 	.render(text, ::sdl2_ttf::blended(color)).ok()
-					//? If THIS worked, we try to make this surface into a texture.
-				//? 	.and_then(|surf| self.renderer.create_texture_from_surface(&surf).ok()
-					//? if *THIS* worked, we can load
-				//? 	.map(Sprite::new))
+					true
+				},
+				Err(_) => false,
+			}
+		}
+
+		pub fn ttf_str_sprite(&mut self, text: &str, font_path: &'static str, size: i32, color: Color) -> Option<Sprite> {
+			if !self.load_font(font_path, size) {
+				return None;
+			}
+
+			//? Both the font and the rendered texture are cached: drawing the
+			//? same string on every frame only costs a hash lookup.
+			let font = &self.cached_fonts[&(font_path, size)];
+			self.text_cache.get_or_render(&self.renderer, font, text, font_path, size, color)
+		}
+
+		/// Builds a `DigitAtlas` which draws numbers with the given font
+		/// without creating a texture for every value.
+		pub fn digit_atlas(&mut self, font_path: &'static str, size: i32, color: Color) -> Option<DigitAtlas> {
+			if !self.load_font(font_path, size) {
+				return None;
+			}
+
+			DigitAtlas::new(&self.renderer, &self.cached_fonts[&(font_path, size)], color)
+		}
+
+		/// Lays `text` out with the glyph atlas of the given font, creating
+		/// the atlas if this is the first time the font is used. The result
+		/// is drawn with `CopyText::copy_text`.
+		///
+		/// Paths ending with `.fnt` are loaded as BMFont bitmap fonts, scaled
+		/// to `size`; anything else is loaded as a TrueType font.
+		pub fn layout_text(&mut self, text: &str, font_path: &'static str, size: i32, options: &TextOptions) -> Option<TextLayout> {
+			let renderer = &self.renderer;
+
+			if font_path.ends_with(".fnt") {
+				if !self.bitmap_fonts.contains_key(font_path) {
+					match BitmapFont::load(renderer, font_path) {
+						Some(font) => { self.bitmap_fonts.insert(font_path, font); },
+						None => return None,
+					}
+				}
+
+				let font = self.bitmap_fonts.get_mut(font_path).unwrap();
+				font.set_size(size);
+				return Some(TextLayout::new(font, renderer, text, options));
+			}
+
+			self.atlases.get(font_path, size, ::sdl2_ttf::STYLE_NORMAL)
+				.map(|atlas| TextLayout::new(atlas, renderer, text, options))
+		}
+
+		/// Parses `markup` (see `markup::parse`) and lays it out, with one
+		/// atlas per combination of styles used by its spans. The result is
+		/// drawn with `CopyRichText::copy_rich_text`.
+		pub fn layout_markup(&mut self, markup: &str, font_path: &'static str, size: i32, align: Align) -> RichLayout {
+			let spans = ::phi::markup::parse(markup);
+			RichLayout::new(&mut self.atlases, &self.renderer, font_path, size, &spans, align)
 		}
 }
 
+impl<'window> Phi<'window> {
+    /// Starts writing the input of every frame to `path`. The random number
+    /// generator is reseeded, and its seed is stored in the replay.
+    pub fn start_recording(&mut self, path: &str) -> ::std::io::Result<()> {
+        self.rng = Rng::from_time();
+        let recorder = try!(Recorder::new(path, self.rng.seed(), &self.events.held_keys()));
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+
+    /// Plays the replay at `path` back: from the next frame on, the input and
+    /// the duration of every frame come from the replay instead of SDL, until
+    /// it ends or `stop_replay` is called. Returns `false` if the replay
+    /// could not be loaded.
+    pub fn play_replay(&mut self, path: &str) -> bool {
+        match Replay::load(path) {
+            Some(replay) => {
+                self.rng = Rng::new(replay.seed);
+                // The replay starts from a state where nothing is held,
+                // and nothing was pressed recently.
+                self.events.release_all();
+                self.events.history.clear();
+                self.input.history.clear();
+                self.playback = Some(replay);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns to live input. Whatever the replay held is released.
+    pub fn stop_replay(&mut self) {
+        if self.playback.take().is_some() {
+            self.events.release_all();
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Whether time is stopped because the window lost the focus. Replays
+    /// are never paused, so that the demo keeps playing in the background.
+    pub fn is_paused(&self) -> bool {
+        self.pause_unfocused && !self.is_replaying() &&
+            (!self.events.focused || self.events.minimized)
+    }
+
+    /// Updates the input for the coming frame, either from SDL or from the
+    /// replay being played, and records it if needed. Returns the duration
+    /// of the frame, which is `elapsed` unless a replay dictates otherwise.
+    fn pump_input(&mut self, elapsed: f64) -> f64 {
+        let frame = match self.playback {
+            Some(ref mut replay) => replay.next_frame(),
+            None => None,
+        };
+
+        let elapsed = match frame {
+            Some(frame) => {
+                self.events.advance(frame.dt);
+                self.events.pump_replay(&mut self.renderer, &frame);
+                frame.dt
+            },
+            None => {
+                // The replay is over, or there was none.
+                self.stop_replay();
+                let elapsed = if self.is_paused() { 0.0 } else { elapsed };
+                self.events.advance(elapsed);
+                self.events.pump(&mut self.renderer);
+
+                //? The window does not receive the releases which happen
+                //? while it is in the background, so whatever is held would
+                //? stay held. The releases are reported in `now`, so they are
+                //? recorded like any other input.
+                if self.events.now.focused == Some(false) ||
+                   self.events.now.minimized == Some(true) {
+                    self.events.release_all();
+                }
+                elapsed
+            },
+        };
+
+        self.input.update(&self.events);
+        self.clock.advance(elapsed);
+
+        let failed = match self.recorder {
+            Some(ref mut recorder) =>
+                recorder.record(&FrameInput::capture(&self.events, elapsed)).is_err(),
+            None => false,
+        };
+        if failed {
+            println!("Could not write the replay, stopping the recording");
+            self.recorder = None;
+        }
+
+        elapsed
+    }
+}
+
 impl<'window> Drop for Phi<'window> {
 	fn drop(&mut self) {
 		::sdl2_image::quit();
@@ -93,6 +341,11 @@ pub enum ViewAction {
     None,
     Quit,
     ChangeView(Box<View>),
+    /// Shows a view on top of the current one, which is kept as it is until
+    /// the new view returns `Pop`.
+    Push(Box<View>),
+    /// Returns to the view below the current one, or quits if there is none.
+    Pop,
 }
 
 pub trait View {
@@ -101,6 +354,14 @@ pub trait View {
     /// 
     /// `elapsed` is expressed in seconds.
     fn render(&mut self, context: &mut Phi, elapsed: f64) -> ViewAction;
+
+    /// Called before `render` when the window loses the focus, which also
+    /// happens when it is minimized; e.g. to open a pause menu. Held input
+    /// was released.
+    fn focus_lost(&mut self, _context: &mut Phi) {}
+
+    /// Called before `render` when the window gets the focus back.
+    fn focus_gained(&mut self, _context: &mut Phi) {}
 }
 
 /// Create a window name `title`, init the underlying libs,
@@ -116,7 +377,7 @@ pub trait View {
 ///
 /// impl View for MyView {
 ///   fn render(&mut self, cxt: &mut Phi, _:f64) -> ViewAction {
-///     if cxt.events.now.quit {
+///     if cxt.events.now.quit || cxt.input.pressed(Action::Back) {
 ///       return ViewAction::Quit;
 ///     }
 ///     cxt.renderer.set_draw_color(Color::RGB(255,255,0));
@@ -133,60 +394,118 @@ pub fn spawn<F>(title: &str, init: F) where F: Fn(&mut Phi) -> Box<View> {
     // Init SDL2
     let sdl_context = ::sdl2::init().unwrap();
     let video = sdl_context.video().unwrap();
-    let mut timer = sdl_context.timer().unwrap();
+    let timer = sdl_context.timer().unwrap();
 		let _ttf_context = ::sdl2_ttf::init();
 
+    // Load the settings, which decide how the window is created
+    let config = Config::load(::phi::config::CONFIG_PATH);
+
     // Create the window
-    let window = video.window(title, 800, 600)
-        .position_centered().opengl().resizable()
-        .build().unwrap();
+    let (w, h) = config.resolution;
+    let mut builder = video.window(title, w, h);
+    builder.position_centered().opengl().resizable();
+    if config.fullscreen {
+        builder.fullscreen_desktop();
+    }
+    let window = builder.build().unwrap();
+
+    let renderer = window.renderer().accelerated();
+    let renderer = if config.vsync { renderer.present_vsync() } else { renderer };
+    let pacing = if config.vsync { Pacing::VSync } else { Pacing::Hybrid };
 
     // Create the context
     let mut context = Phi::new(
-        Events::new(sdl_context.event_pump().unwrap()),
-        window.renderer().accelerated().build().unwrap(),
+        Events::new(
+            sdl_context.event_pump().unwrap(),
+            events::Gamepads::new(sdl_context.game_controller().unwrap()),
+            video.text_input(),
+        ),
+        renderer.build().unwrap(),
+        config,
+        FrameClock::new(timer, pacing),
     );
 
-    // Create the default view
-    let mut current_view = init(&mut context);
+    // Create the default view. Only the view on top of the stack is rendered.
+    let mut views: Vec<Box<View>> = vec![init(&mut context)];
+    let mut pauses = PauseStack::new();
     // Frame timing 
-    let interval = 1_000 / 60;
-    let mut before = timer.ticks();
-    let mut last_second = timer.ticks();
+    let mut last_second = context.clock.now();
     let mut fps = 0u16;
 
     loop {
-        // Frame timing (bis)
-        let now = timer.ticks();
-        let dt = now - before;
-        let elapsed = dt as f64 / 1_000.0;
-        // If the time elapsed since last frame is too small
-        // wait out the diff and try again
-        if dt < interval {
-            timer.delay(interval - dt);
-            continue;
-        }
-
-        before = now;
+        // Frame timing (bis): wait for the next frame, as paced by the clock.
+        let elapsed = context.clock.wait_next_frame();
         fps += 1;
 
-        if now - last_second > 1_000 {
+        let now = context.clock.now();
+        if now - last_second > 1.0 {
             println!("FPS: {}", fps);
             last_second = now;
             fps = 0;
         }
+        context.profiler.begin_frame();
+        context.text_cache.begin_frame();
+
         // Pass the renderer to the pump to handle window resizing.
-        context.events.pump(&mut context.renderer);
+        context.profiler.begin("events");
+        let elapsed = context.pump_input(elapsed);
+        console::update(&mut context);
+        tween::update(&mut context);
+        context.debug.begin_frame(&context.events, elapsed);
+        context.debug_draw.begin_frame(&context.events);
+        if context.events.now.keys.contains(&(profiler::TOGGLE_KEY, true)) {
+            context.profiler.visible = !context.profiler.visible;
+        }
+        context.profiler.end();
+
+        match context.events.now.focused {
+            Some(false) => views.last_mut().unwrap().focus_lost(&mut context),
+            Some(true) => views.last_mut().unwrap().focus_gained(&mut context),
+            None => {},
+        }
+
+        //? The action is bound first, so that `views` is no longer borrowed
+        //? when it is changed.
+        context.profiler.begin("view");
+        let action = views.last_mut().unwrap().render(&mut context, elapsed);
+        context.profiler.end();
 
-        match current_view.render(&mut context, elapsed) {
-            ViewAction::None => 
-                context.renderer.present(),
+        match action {
+            ViewAction::None => {
+                let screenshot_key = context.events.now.keys.contains(&(capture::SCREENSHOT_KEY, true));
+                context.capture.capture(&context.renderer, screenshot_key);
+
+                context.profiler.begin("overlays");
+                debug::render(&mut context);
+                profiler::render(&mut context);
+                console::render(&mut context);
+                context.profiler.end();
+
+                context.profiler.begin("present");
+                context.renderer.present();
+                context.profiler.end();
+            },
 
             ViewAction::Quit => 
                 break,
 
-            ViewAction::ChangeView(new_view) =>
-                current_view = new_view,
+            ViewAction::ChangeView(new_view) => {
+                context.clock.paused = false;
+                *views.last_mut().unwrap() = new_view;
+            },
+
+            ViewAction::Push(new_view) => {
+                pauses.push(context.clock.paused);
+                views.push(new_view);
+            },
+
+            ViewAction::Pop => {
+                views.pop();
+                if views.is_empty() {
+                    break;
+                }
+                context.clock.paused = pauses.pop();
+            },
         }
     }
 }
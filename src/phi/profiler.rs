@@ -0,0 +1,509 @@
+use ::phi::Phi;
+use ::phi::data::Rectangle;
+use ::phi::text::{Align, CopyText, TextOptions};
+
+use ::std::collections::VecDeque;
+use ::std::fs::File;
+use ::std::io::{self, Write};
+use ::std::time::Instant;
+
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::pixels::Color;
+use ::sdl2::render::BlendMode;
+
+/// The key which shows and hides the graph.
+pub const TOGGLE_KEY: Keycode = Keycode::F3;
+
+const FONT: &'static str = "assets/belligerent.ttf";
+const FONT_SIZE: i32 = 14;
+const LINE_H: f64 = 16.0;
+/// How far nested scopes are indented in the legend, per level.
+const LEGEND_INDENT: f64 = 12.0;
+
+/// How many frames the statistics and the graph cover.
+const HISTORY: usize = 240;
+
+/// The graph has a column of one pixel per frame, and is scaled so that
+/// `GRAPH_MS` fill its height.
+const GRAPH_H: f64 = 100.0;
+const GRAPH_MS: f64 = 50.0;
+/// The duration of a frame at 60 fps, marked on the graph.
+const BUDGET_MS: f64 = 1000.0 / 60.0;
+
+/// The colors of the top-level scopes, in the order in which they were
+/// first seen.
+const PALETTE: [(u8, u8, u8); 6] = [
+	(80, 160, 255),
+	(255, 204, 51),
+	(120, 220, 120),
+	(230, 90, 90),
+	(200, 120, 230),
+	(90, 220, 220),
+];
+
+/// A scope which ended during the current frame. Times are in microseconds
+/// since the profiler was created.
+struct Span {
+	name: &'static str,
+	start: f64,
+	duration: f64,
+	depth: usize,
+	/// The scope this one was nested in, if any.
+	parent: Option<&'static str>,
+}
+
+/// What is kept of a frame once it is over, in milliseconds.
+struct FrameRecord {
+	total: f64,
+	/// The time spent in each top-level scope.
+	scopes: Vec<(&'static str, f64)>,
+	/// The time spent in each nested scope, by parent and name.
+	nested: Vec<((&'static str, &'static str), f64)>,
+}
+
+/// A line of the legend: a scope, indented by `depth` under its `parent`,
+/// and drawn in the color of its top-level scope, `root`.
+struct LegendLine {
+	depth: usize,
+	parent: Option<&'static str>,
+	name: &'static str,
+	root: &'static str,
+}
+
+/// A trace being recorded, written to `path` once `frames_left` is 0.
+struct Trace {
+	path: String,
+	frames_left: u32,
+	spans: Vec<Span>,
+}
+
+/// Rolling statistics on the duration of frames, in milliseconds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats {
+	pub min: f64,
+	pub avg: f64,
+	pub max: f64,
+	/// 99% of the frames took at most this long.
+	pub p99: f64,
+}
+
+/// Measures where the time of each frame goes.
+///
+/// `spawn` times the event pump, the view, the overlays and the present,
+/// and views may time their own parts by surrounding them with `begin` and
+/// `end`. Scopes can be nested: the graph only splits frames by top-level
+/// scope, and the legend lists nested ones under their parent. The frame
+/// itself lasts from one call of `begin_frame` to the next, so it includes
+/// the wait for the next frame.
+pub struct Profiler {
+	/// Whether the graph is drawn, toggled with F3.
+	pub visible: bool,
+	origin: Instant,
+	/// When the current frame started, or `None` before the first one.
+	frame_start: Option<f64>,
+	/// The scopes which have begun but not ended yet, innermost last.
+	open: Vec<(&'static str, f64)>,
+	spans: Vec<Span>,
+	frames: VecDeque<FrameRecord>,
+	/// The names of the top-level scopes, which give them their color.
+	names: Vec<&'static str>,
+	/// The nested scopes seen so far, by parent and name.
+	children: Vec<(&'static str, &'static str)>,
+	trace: Option<Trace>,
+}
+
+impl Profiler {
+	pub fn new() -> Profiler {
+		Profiler {
+			visible: false,
+			origin: Instant::now(),
+			frame_start: None,
+			open: Vec::new(),
+			spans: Vec::new(),
+			frames: VecDeque::new(),
+			names: Vec::new(),
+			children: Vec::new(),
+			trace: None,
+		}
+	}
+
+	/// The time since the profiler was created, in microseconds.
+	fn now(&self) -> f64 {
+		let since = self.origin.elapsed();
+		since.as_secs() as f64 * 1_000_000.0 + since.subsec_nanos() as f64 / 1_000.0
+	}
+
+	/// Ends the previous frame, whose statistics are updated, and starts a
+	/// new one. Called by `spawn` at the start of every frame.
+	pub fn begin_frame(&mut self) {
+		// Scopes which were not ended stop with the frame.
+		while !self.open.is_empty() {
+			self.end();
+		}
+
+		let now = self.now();
+		if let Some(start) = self.frame_start {
+			self.finish_frame(start, now);
+		}
+		self.frame_start = Some(now);
+	}
+
+	fn finish_frame(&mut self, start: f64, now: f64) {
+		let spans = ::std::mem::replace(&mut self.spans, Vec::new());
+
+		let mut scopes: Vec<(&'static str, f64)> = Vec::new();
+		for span in spans.iter().filter(|span| span.depth == 0) {
+			if !self.names.contains(&span.name) {
+				self.names.push(span.name);
+			}
+			let seen = scopes.iter().position(|&(name, _)| name == span.name);
+			match seen {
+				Some(i) => scopes[i].1 += span.duration / 1000.0,
+				None => scopes.push((span.name, span.duration / 1000.0)),
+			}
+		}
+
+		let mut nested: Vec<((&'static str, &'static str), f64)> = Vec::new();
+		for span in &spans {
+			let key = match span.parent {
+				Some(parent) => (parent, span.name),
+				None => continue,
+			};
+			if !self.children.contains(&key) {
+				self.children.push(key);
+			}
+			let seen = nested.iter().position(|&(k, _)| k == key);
+			match seen {
+				Some(i) => nested[i].1 += span.duration / 1000.0,
+				None => nested.push((key, span.duration / 1000.0)),
+			}
+		}
+
+		self.frames.push_back(FrameRecord {
+			total: (now - start) / 1000.0,
+			scopes: scopes,
+			nested: nested,
+		});
+		if self.frames.len() > HISTORY {
+			self.frames.pop_front();
+		}
+
+		let done = match self.trace {
+			Some(ref mut trace) => {
+				trace.spans.push(Span {
+					name: "frame",
+					start: start,
+					duration: now - start,
+					depth: 0,
+					parent: None,
+				});
+				trace.spans.extend(spans);
+				trace.frames_left -= 1;
+				trace.frames_left == 0
+			},
+			None => false,
+		};
+		if done {
+			let trace = self.trace.take().unwrap();
+			match write_trace(&trace) {
+				Ok(()) => println!("Wrote the trace to {}", trace.path),
+				Err(e) => println!("Could not write the trace to {}: {}", trace.path, e),
+			}
+		}
+	}
+
+	/// Starts timing the scope called `name`, until the matching `end`.
+	pub fn begin(&mut self, name: &'static str) {
+		let now = self.now();
+		self.open.push((name, now));
+	}
+
+	/// Stops timing the scope which was begun last.
+	pub fn end(&mut self) {
+		let now = self.now();
+		if let Some((name, start)) = self.open.pop() {
+			self.spans.push(Span {
+				name: name,
+				start: start,
+				duration: now - start,
+				depth: self.open.len(),
+				parent: self.open.last().map(|&(parent, _)| parent),
+			});
+		}
+	}
+
+	/// The statistics of the last frames.
+	pub fn stats(&self) -> Stats {
+		if self.frames.is_empty() {
+			return Stats::default();
+		}
+
+		let mut totals: Vec<f64> = self.frames.iter().map(|frame| frame.total).collect();
+		totals.sort_by(|a, b| a.partial_cmp(b).unwrap());
+		let sum = totals.iter().fold(0.0, |sum, t| sum + t);
+		let p99 = ((totals.len() as f64 * 0.99).ceil() as usize).saturating_sub(1);
+
+		Stats {
+			min: totals[0],
+			avg: sum / totals.len() as f64,
+			max: totals[totals.len() - 1],
+			p99: totals[p99],
+		}
+	}
+
+	/// The average time spent in the top-level scope `name` during the last
+	/// frames, in milliseconds.
+	pub fn scope_average(&self, name: &str) -> f64 {
+		if self.frames.is_empty() {
+			return 0.0;
+		}
+
+		let sum = self.frames.iter()
+			.flat_map(|frame| frame.scopes.iter())
+			.filter(|&&(scope, _)| scope == name)
+			.fold(0.0, |sum, &(_, time)| sum + time);
+		sum / self.frames.len() as f64
+	}
+
+	/// The average time spent in the scope `name` nested in `parent` during
+	/// the last frames, in milliseconds.
+	pub fn nested_average(&self, parent: &str, name: &str) -> f64 {
+		if self.frames.is_empty() {
+			return 0.0;
+		}
+
+		let sum = self.frames.iter()
+			.flat_map(|frame| frame.nested.iter())
+			.filter(|&&((p, n), _)| p == parent && n == name)
+			.fold(0.0, |sum, &(_, time)| sum + time);
+		sum / self.frames.len() as f64
+	}
+
+	/// Every scope seen so far, each top-level one followed by the scopes
+	/// nested in it.
+	fn legend(&self) -> Vec<LegendLine> {
+		let mut lines = Vec::new();
+		for &name in &self.names {
+			lines.push(LegendLine { depth: 0, parent: None, name: name, root: name });
+			self.legend_children(&mut vec![name], name, &mut lines);
+		}
+		lines
+	}
+
+	/// Adds the scopes nested in the last of `path` to `lines`, recursively.
+	fn legend_children(&self, path: &mut Vec<&'static str>, root: &'static str, lines: &mut Vec<LegendLine>) {
+		let parent = *path.last().unwrap();
+		for &(p, name) in &self.children {
+			//? A scope nested in itself, however deeply, would never end.
+			if p != parent || path.contains(&name) {
+				continue;
+			}
+			lines.push(LegendLine { depth: path.len(), parent: Some(parent), name: name, root: root });
+			path.push(name);
+			self.legend_children(path, root, lines);
+			path.pop();
+		}
+	}
+
+	/// Records every scope of the next `frames` frames, then writes them to
+	/// `path` in the trace format of Chrome, which `chrome://tracing` opens.
+	pub fn start_trace(&mut self, path: &str, frames: u32) {
+		self.trace = Some(Trace {
+			path: path.to_string(),
+			frames_left: ::std::cmp::max(frames, 1),
+			spans: Vec::new(),
+		});
+	}
+
+	pub fn is_tracing(&self) -> bool {
+		self.trace.is_some()
+	}
+
+	fn color(&self, name: &str) -> Color {
+		let i = self.names.iter().position(|&n| n == name).unwrap_or(0);
+		let (r, g, b) = PALETTE[i % PALETTE.len()];
+		Color::RGB(r, g, b)
+	}
+}
+
+fn write_trace(trace: &Trace) -> io::Result<()> {
+	let mut file = try!(File::create(&trace.path));
+	try!(writeln!(file, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":["));
+
+	for (i, span) in trace.spans.iter().enumerate() {
+		let separator = if i + 1 < trace.spans.len() { "," } else { "" };
+		//? Scope names are string literals, so they need no escaping.
+		try!(writeln!(file,
+			"{{\"name\":\"{}\",\"cat\":\"phi\",\"ph\":\"X\",\"ts\":{:.1},\"dur\":{:.1},\"pid\":1,\"tid\":1}}{}",
+			span.name, span.start, span.duration, separator));
+	}
+
+	writeln!(file, "]}}")
+}
+
+/// Draws the graph of the last frames in the bottom-left corner of the
+/// window, if it is visible: a column per frame, split by top-level scope,
+/// with the statistics and the average of every scope above it, nested ones
+/// being indented under their parent. Called by
+/// `spawn` after the view is rendered.
+pub fn render(phi: &mut Phi) {
+	if !phi.profiler.visible {
+		return;
+	}
+
+	let (_, win_h) = phi.output_size();
+	let width = HISTORY as f64;
+	let legend = phi.profiler.legend();
+	let lines = legend.len() + 1;
+
+	let graph = Rectangle {
+		x: 10.0,
+		y: win_h as f64 - GRAPH_H - 10.0,
+		w: width,
+		h: GRAPH_H,
+	};
+	let top = graph.y - LINE_H * lines as f64 - 4.0;
+
+	phi.renderer.set_blend_mode(BlendMode::Blend);
+	phi.renderer.set_draw_color(Color::RGBA(0,0,0,180));
+	phi.renderer.fill_rect(Rectangle {
+		x: graph.x - 4.0,
+		y: top - 4.0,
+		w: width + 8.0,
+		h: graph.y + graph.h - top + 8.0,
+	}.to_sdl().unwrap());
+	phi.renderer.set_blend_mode(BlendMode::None);
+
+	let scale = GRAPH_H / GRAPH_MS;
+	for (i, frame) in phi.profiler.frames.iter().enumerate() {
+		let x = graph.x + i as f64;
+		let mut bottom = graph.y + graph.h;
+
+		// What is not in a scope, mostly waiting for the next frame, is grey.
+		let column = |from: f64, ms: f64| Rectangle {
+			x: x,
+			y: from - ms * scale,
+			w: 1.0,
+			h: ms * scale,
+		}.to_sdl();
+
+		phi.renderer.set_draw_color(Color::RGB(90,90,90));
+		if let Some(rect) = column(bottom, frame.total.min(GRAPH_MS)) {
+			phi.renderer.fill_rect(rect);
+		}
+		for &(name, ms) in &frame.scopes {
+			// Clipped to the top of the graph.
+			let ms = ms.min(((bottom - graph.y) / scale).max(0.0));
+			phi.renderer.set_draw_color(phi.profiler.color(name));
+			if let Some(rect) = column(bottom, ms) {
+				phi.renderer.fill_rect(rect);
+			}
+			bottom -= ms * scale;
+		}
+	}
+
+	// The budget of a frame at 60 fps.
+	let budget = graph.y + graph.h - BUDGET_MS * scale;
+	phi.renderer.set_draw_color(Color::RGB(255,255,255));
+	phi.renderer.fill_rect(Rectangle {
+		x: graph.x,
+		y: budget,
+		w: width,
+		h: 1.0,
+	}.to_sdl().unwrap());
+
+	let stats = phi.profiler.stats();
+	let options = TextOptions::aligned(Align::Left, width);
+	let summary = format!("{:.1} / {:.1} / {:.1} / {:.1} ms (min/avg/max/p99)",
+		stats.min, stats.avg, stats.max, stats.p99);
+	if let Some(layout) = phi.layout_text(&summary, FONT, FONT_SIZE, &options) {
+		phi.renderer.copy_text(&layout, graph.x, top, Color::RGB(255,255,255));
+	}
+
+	for (i, line) in legend.into_iter().enumerate() {
+		let average = match line.parent {
+			Some(parent) => phi.profiler.nested_average(parent, line.name),
+			None => phi.profiler.scope_average(line.name),
+		};
+		let text = format!("{}: {:.2} ms", line.name, average);
+		let color = phi.profiler.color(line.root);
+		let x = graph.x + LEGEND_INDENT * line.depth as f64;
+		if let Some(layout) = phi.layout_text(&text, FONT, FONT_SIZE, &options) {
+			phi.renderer.copy_text(&layout, x, top + LINE_H * (i + 1) as f64, color);
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{FrameRecord, Profiler, Span};
+
+	fn close(a: f64, b: f64) -> bool {
+		(a - b).abs() < 1e-9
+	}
+
+	fn frame(total: f64) -> FrameRecord {
+		FrameRecord {
+			total: total,
+			scopes: Vec::new(),
+			nested: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn stats_of_the_last_frames() {
+		let mut profiler = Profiler::new();
+		assert!(close(profiler.stats().p99, 0.0));
+
+		//? 1 to 100 ms, in no particular order.
+		for i in 0..100 {
+			profiler.frames.push_back(frame(((i * 37) % 100 + 1) as f64));
+		}
+		let stats = profiler.stats();
+		assert!(close(stats.min, 1.0));
+		assert!(close(stats.max, 100.0));
+		assert!(close(stats.avg, 50.5));
+		assert!(close(stats.p99, 99.0));
+
+		//? A single slow frame is the p99 of fewer than 100 frames.
+		let mut profiler = Profiler::new();
+		for i in 0..50 {
+			profiler.frames.push_back(frame(if i == 10 { 40.0 } else { 10.0 }));
+		}
+		assert!(close(profiler.stats().p99, 40.0));
+	}
+
+	#[test]
+	fn nested_scopes_are_listed_under_their_parent() {
+		let mut profiler = Profiler::new();
+		let span = |name, depth, parent, duration| Span {
+			name: name,
+			start: 0.0,
+			duration: duration,
+			depth: depth,
+			parent: parent,
+		};
+		//? Inner scopes end, and are pushed, before outer ones.
+		profiler.spans = vec![
+			span("physics", 1, Some("view"), 2000.0),
+			span("physics", 1, Some("view"), 1000.0),
+			span("draw", 1, Some("view"), 4000.0),
+			span("view", 0, None, 8000.0),
+			span("present", 0, None, 1000.0),
+		];
+		profiler.finish_frame(0.0, 16000.0);
+
+		let legend: Vec<_> = profiler.legend().into_iter()
+			.map(|line| (line.depth, line.name, line.root))
+			.collect();
+		assert_eq!(legend, vec![
+			(0, "view", "view"),
+			(1, "physics", "view"),
+			(1, "draw", "view"),
+			(0, "present", "present"),
+		]);
+		assert!(close(profiler.scope_average("view"), 8.0));
+		assert!(close(profiler.nested_average("view", "physics"), 3.0));
+	}
+}
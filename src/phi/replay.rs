@@ -0,0 +1,410 @@
+use ::phi::Events;
+use ::phi::events::EditKey;
+
+use ::std::collections::VecDeque;
+use ::std::fs::File;
+use ::std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+use ::num::traits::FromPrimitive;
+
+use ::sdl2::controller::{Axis, Button};
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::mouse::Mouse as MouseButton;
+
+/// The first word of every replay file, followed by the format version.
+const MAGIC: &'static str = "arcade-replay";
+const VERSION: u32 = 1;
+
+/// The input received during a single frame, along with the time it lasted.
+///
+/// Only the transitions are stored: what is held is rebuilt by applying
+/// every frame in order, starting from the keys held when recording began.
+/// Touches and input method compositions are not recorded.
+#[derive(Clone, Debug)]
+pub struct FrameInput {
+	pub dt: f64,
+	pub keys: Vec<(Keycode, bool)>,
+	pub buttons: Vec<(usize, Button, bool)>,
+	pub axes: Vec<(usize, Axis, i16)>,
+	/// Where the mouse moved to, if it moved.
+	pub mouse: Option<(f64, f64)>,
+	pub mouse_buttons: Vec<(MouseButton, bool)>,
+	pub wheel: (i32, i32),
+	pub text: String,
+	pub edit_keys: Vec<EditKey>,
+	pub quit: bool,
+}
+
+impl FrameInput {
+	fn empty(dt: f64) -> FrameInput {
+		FrameInput {
+			dt: dt,
+			keys: Vec::new(),
+			buttons: Vec::new(),
+			axes: Vec::new(),
+			mouse: None,
+			mouse_buttons: Vec::new(),
+			wheel: (0, 0),
+			text: String::new(),
+			edit_keys: Vec::new(),
+			quit: false,
+		}
+	}
+
+	/// Records what happened during the frame which was just pumped.
+	pub fn capture(events: &Events, dt: f64) -> FrameInput {
+		let now = &events.now;
+		let mut frame = FrameInput::empty(dt);
+
+		frame.keys = now.keys.clone();
+		frame.buttons = now.buttons.clone();
+		frame.axes = now.axes.clone();
+		if now.mouse.moved {
+			frame.mouse = Some((events.mouse.x, events.mouse.y));
+		}
+		for &(button, state) in [
+			(MouseButton::Left, now.mouse.left),
+			(MouseButton::Middle, now.mouse.middle),
+			(MouseButton::Right, now.mouse.right),
+		].iter() {
+			if let Some(pressed) = state {
+				frame.mouse_buttons.push((button, pressed));
+			}
+		}
+		frame.wheel = now.mouse.wheel;
+		frame.text = now.text.clone();
+		frame.edit_keys = now.edit_keys.clone();
+		frame.quit = now.quit;
+
+		frame
+	}
+
+	/// Feeds the recorded input to `events`, as if it came from SDL.
+	pub fn apply(&self, events: &mut Events) {
+		for &(key, pressed) in &self.keys {
+			if pressed { events.press_key(key) } else { events.release_key(key) }
+		}
+		for &(player, button, pressed) in &self.buttons {
+			events.set_button(player, button, pressed);
+		}
+		for &(player, axis, value) in &self.axes {
+			events.set_axis(player, axis, value);
+		}
+		if let Some((x, y)) = self.mouse {
+			events.move_mouse(x, y);
+		}
+		for &(button, pressed) in &self.mouse_buttons {
+			events.set_mouse_button(button, pressed);
+		}
+		events.now.mouse.wheel = self.wheel;
+		if !self.text.is_empty() {
+			events.type_text(&self.text);
+		}
+		events.now.edit_keys.extend(self.edit_keys.iter().cloned());
+		events.now.quit = events.now.quit || self.quit;
+	}
+
+	/// Writes the frame as a single line: its duration followed by one token
+	/// per input, e.g. `0.016667 k+1073741906 b+0:a m120:33`.
+	fn encode(&self) -> String {
+		let mut line = format!("{}", self.dt);
+
+		for &(key, pressed) in &self.keys {
+			line.push_str(&format!(" k{}{}", sign(pressed), key as i32));
+		}
+		for &(player, button, pressed) in &self.buttons {
+			line.push_str(&format!(" b{}{}:{}", sign(pressed), player, button.string()));
+		}
+		for &(player, axis, value) in &self.axes {
+			line.push_str(&format!(" a{}:{}:{}", player, axis.string(), value));
+		}
+		if let Some((x, y)) = self.mouse {
+			line.push_str(&format!(" m{}:{}", x, y));
+		}
+		for &(button, pressed) in &self.mouse_buttons {
+			line.push_str(&format!(" M{}{}", sign(pressed), match button {
+				MouseButton::Left => 'L',
+				MouseButton::Middle => 'M',
+				_ => 'R',
+			}));
+		}
+		if self.wheel != (0, 0) {
+			line.push_str(&format!(" w{}:{}", self.wheel.0, self.wheel.1));
+		}
+		if !self.text.is_empty() {
+			//? Typed text may contain spaces, so it is stored as hexadecimal.
+			let hex: Vec<String> = self.text.bytes().map(|b| format!("{:02x}", b)).collect();
+			line.push_str(&format!(" t{}", hex.concat()));
+		}
+		for key in &self.edit_keys {
+			line.push_str(&format!(" e{}", edit_key_code(*key)));
+		}
+		if self.quit {
+			line.push_str(" q");
+		}
+
+		line
+	}
+
+	/// Parses a line written by `encode`. Unknown tokens are skipped, so that
+	/// a replay stays usable if a binding name changed.
+	fn decode(line: &str) -> Option<FrameInput> {
+		let mut tokens = line.split(' ');
+		let dt = match tokens.next().and_then(|dt| dt.parse().ok()) {
+			Some(dt) => dt,
+			None => return None,
+		};
+		let mut frame = FrameInput::empty(dt);
+
+		for token in tokens.filter(|t| !t.is_empty() && t.is_char_boundary(1)) {
+			let (kind, rest) = token.split_at(1);
+			let pressed = rest.starts_with('+');
+			//? For tokens which start with a sign, what follows it.
+			let body = if pressed || rest.starts_with('-') { &rest[1..] } else { rest };
+
+			match kind {
+				"k" => {
+					if let Some(key) = body.parse().ok().and_then(Keycode::from_i32) {
+						frame.keys.push((key, pressed));
+					}
+				},
+				"b" => {
+					let mut parts = body.splitn(2, ':');
+					let player = parts.next().and_then(|p| p.parse().ok());
+					let button = parts.next().and_then(Button::from_string);
+					if let (Some(player), Some(button)) = (player, button) {
+						frame.buttons.push((player, button, pressed));
+					}
+				},
+				"a" => {
+					let parts: Vec<&str> = rest.split(':').collect();
+					if parts.len() == 3 {
+						let player = parts[0].parse().ok();
+						let axis = Axis::from_string(parts[1]);
+						let value = parts[2].parse().ok();
+						if let (Some(player), Some(axis), Some(value)) = (player, axis, value) {
+							frame.axes.push((player, axis, value));
+						}
+					}
+				},
+				"m" => {
+					let mut parts = rest.splitn(2, ':');
+					let x = parts.next().and_then(|x| x.parse().ok());
+					let y = parts.next().and_then(|y| y.parse().ok());
+					if let (Some(x), Some(y)) = (x, y) {
+						frame.mouse = Some((x, y));
+					}
+				},
+				"M" => {
+					let button = match body {
+						"L" => MouseButton::Left,
+						"M" => MouseButton::Middle,
+						_ => MouseButton::Right,
+					};
+					frame.mouse_buttons.push((button, pressed));
+				},
+				"w" => {
+					let mut parts = rest.splitn(2, ':');
+					let x = parts.next().and_then(|x| x.parse().ok());
+					let y = parts.next().and_then(|y| y.parse().ok());
+					if let (Some(x), Some(y)) = (x, y) {
+						frame.wheel = (x, y);
+					}
+				},
+				"t" => {
+					let bytes: Vec<u8> = (0..rest.len() / 2)
+						.filter_map(|i| u8::from_str_radix(&rest[i * 2..i * 2 + 2], 16).ok())
+						.collect();
+					frame.text = String::from_utf8_lossy(&bytes).into_owned();
+				},
+				"e" => {
+					if let Some(key) = rest.parse().ok().and_then(edit_key_from_code) {
+						frame.edit_keys.push(key);
+					}
+				},
+				"q" => frame.quit = true,
+				_ => {},
+			}
+		}
+
+		Some(frame)
+	}
+}
+
+fn sign(pressed: bool) -> char {
+	if pressed { '+' } else { '-' }
+}
+
+const EDIT_KEYS: [EditKey; 7] = [
+	EditKey::Backspace,
+	EditKey::Delete,
+	EditKey::Left,
+	EditKey::Right,
+	EditKey::Home,
+	EditKey::End,
+	EditKey::Submit,
+];
+
+fn edit_key_code(key: EditKey) -> usize {
+	EDIT_KEYS.iter().position(|k| *k == key).unwrap()
+}
+
+fn edit_key_from_code(code: usize) -> Option<EditKey> {
+	EDIT_KEYS.get(code).cloned()
+}
+
+
+/// Writes the input of every frame to a replay file, as it is played.
+pub struct Recorder {
+	file: BufWriter<File>,
+}
+
+impl Recorder {
+	/// Creates the replay file at `path`. `seed` is the seed of `Phi::rng`,
+	/// and `held` lists the keys which are held when the recording starts.
+	pub fn new(path: &str, seed: u64, held: &[Keycode]) -> io::Result<Recorder> {
+		let mut file = BufWriter::new(try!(File::create(path)));
+
+		try!(writeln!(file, "{} {} {}", MAGIC, VERSION, seed));
+		let held: Vec<String> = held.iter().map(|key| (*key as i32).to_string()).collect();
+		try!(writeln!(file, "held {}", held.join(" ")));
+
+		Ok(Recorder {
+			file: file,
+		})
+	}
+
+	pub fn record(&mut self, frame: &FrameInput) -> io::Result<()> {
+		writeln!(self.file, "{}", frame.encode())
+	}
+}
+
+
+/// A replay file loaded in memory, which hands out its frames in order.
+pub struct Replay {
+	pub seed: u64,
+	frames: VecDeque<FrameInput>,
+}
+
+impl Replay {
+	/// Loads the replay at `path`. Returns `None` if the file cannot be read
+	/// or was not written by a `Recorder` of the same version.
+	pub fn load(path: &str) -> Option<Replay> {
+		let file = match File::open(path) {
+			Ok(file) => file,
+			Err(_) => return None,
+		};
+		let mut lines = BufReader::new(file).lines().filter_map(|line| line.ok());
+
+		let header = match lines.next() {
+			Some(header) => header,
+			None => return None,
+		};
+		let header: Vec<&str> = header.split(' ').collect();
+		if header.len() != 3 || header[0] != MAGIC || header[1] != VERSION.to_string() {
+			return None;
+		}
+		let seed = match header[2].parse() {
+			Ok(seed) => seed,
+			Err(_) => return None,
+		};
+
+		//? The keys held when the recording started are pressed during the
+		//? first frame, so that the player holds them as well.
+		let held: Vec<Keycode> = lines.next().unwrap_or(String::new())
+			.split(' ')
+			.skip(1)
+			.filter_map(|code| code.parse().ok().and_then(Keycode::from_i32))
+			.collect();
+
+		let mut frames: VecDeque<FrameInput> = lines
+			.filter_map(|line| FrameInput::decode(&line))
+			.collect();
+
+		if let Some(first) = frames.front_mut() {
+			let recorded = ::std::mem::replace(&mut first.keys, Vec::new());
+			first.keys = held.into_iter().map(|key| (key, true)).chain(recorded).collect();
+		}
+
+		Some(Replay {
+			seed: seed,
+			frames: frames,
+		})
+	}
+
+	pub fn next_frame(&mut self) -> Option<FrameInput> {
+		self.frames.pop_front()
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.frames.is_empty()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{FrameInput, Recorder, Replay};
+	use ::phi::events::EditKey;
+
+	use ::sdl2::keyboard::Keycode;
+	use ::sdl2::mouse::Mouse as MouseButton;
+
+	#[test]
+	fn encode_then_decode() {
+		let mut frame = FrameInput::empty(0.016);
+		frame.keys = vec![(Keycode::Up, true), (Keycode::Space, false)];
+		frame.mouse = Some((-12.5, 33.0));
+		frame.mouse_buttons = vec![(MouseButton::Left, true), (MouseButton::Right, false)];
+		frame.wheel = (0, -2);
+		frame.text = "a b\u{e9}".to_string();
+		frame.edit_keys = vec![EditKey::Backspace, EditKey::Submit];
+		frame.quit = true;
+
+		let line = frame.encode();
+		let decoded = FrameInput::decode(&line).unwrap();
+		assert_eq!(decoded.encode(), line);
+		assert_eq!(decoded.keys, frame.keys);
+		assert_eq!(decoded.mouse, frame.mouse);
+		assert_eq!(decoded.wheel, frame.wheel);
+		assert_eq!(decoded.text, frame.text);
+		assert_eq!(decoded.edit_keys, frame.edit_keys);
+		assert!(decoded.quit);
+	}
+
+	#[test]
+	fn decode_skips_unknown_tokens() {
+		let frame = FrameInput::decode("0.5 z k+1073741906 k+nope  q").unwrap();
+		assert_eq!(frame.keys, vec![(Keycode::Up, true)]);
+		assert!(frame.quit);
+
+		assert!(FrameInput::decode("k+1073741906").is_none());
+	}
+
+	#[test]
+	fn record_then_load() {
+		let path = ::std::env::temp_dir().join("arcade-rs-test.replay");
+		let path = path.to_string_lossy().into_owned();
+		{
+			let mut recorder = Recorder::new(&path, 42, &[Keycode::Left]).unwrap();
+			let mut frame = FrameInput::empty(0.25);
+			frame.keys = vec![(Keycode::Up, true)];
+			recorder.record(&frame).unwrap();
+			recorder.record(&FrameInput::empty(0.5)).unwrap();
+		}
+
+		let mut replay = Replay::load(&path).unwrap();
+		assert_eq!(replay.seed, 42);
+		//? The keys held when recording started are pressed on the first frame.
+		assert_eq!(replay.next_frame().unwrap().keys, vec![(Keycode::Left, true), (Keycode::Up, true)]);
+		assert!(replay.next_frame().unwrap().keys.is_empty());
+		assert!(replay.is_finished());
+	}
+	#[test]
+	fn shipped_replays_load() {
+		for path in &["assets/demo.replay", "assets/golden/ship_edges.replay"] {
+			let replay = Replay::load(path).unwrap();
+			assert!(!replay.is_finished(), "{} has no frames", path);
+		}
+	}
+}
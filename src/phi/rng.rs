@@ -0,0 +1,52 @@
+/// A small and fast pseudo-random number generator (xorshift64*).
+///
+/// Gameplay code should draw its random numbers from `Phi::rng` rather than
+/// from any other source: its seed is stored in replays, so that a replayed
+/// session makes the same random choices as the recorded one.
+#[derive(Clone, Debug)]
+pub struct Rng {
+	seed: u64,
+	state: u64,
+}
+
+impl Rng {
+	pub fn new(seed: u64) -> Rng {
+		Rng {
+			seed: seed,
+			//? xorshift gets stuck at 0, so we make sure to never start there.
+			state: if seed == 0 { 0x9E3779B97F4A7C15 } else { seed },
+		}
+	}
+
+	/// Seeds a generator with the current time.
+	pub fn from_time() -> Rng {
+		let now = ::std::time::SystemTime::now()
+			.duration_since(::std::time::UNIX_EPOCH)
+			.map(|d| d.as_secs().wrapping_mul(1_000_000_000) + d.subsec_nanos() as u64)
+			.unwrap_or(0);
+
+		Rng::new(now)
+	}
+
+	/// The seed this generator was created with.
+	pub fn seed(&self) -> u64 {
+		self.seed
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state ^= self.state >> 12;
+		self.state ^= self.state << 25;
+		self.state ^= self.state >> 27;
+		self.state.wrapping_mul(0x2545F4914F6CDD1D)
+	}
+
+	/// A number in `[0, 1)`.
+	pub fn next_f64(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	/// A number in `[min, max)`.
+	pub fn range(&mut self, min: f64, max: f64) -> f64 {
+		min + (max - min) * self.next_f64()
+	}
+}
@@ -0,0 +1,836 @@
+use ::phi::data::Rectangle;
+use ::phi::gfx::{self, CopySprite, Sprite};
+
+use ::std::collections::hash_map::HashMap;
+use ::std::path::Path;
+
+use ::sdl2::pixels::{Color, PixelFormatEnum};
+use ::sdl2::rect::Rect as SdlRect;
+use ::sdl2::render::{BlendMode, Renderer};
+use ::sdl2::surface::Surface;
+use ::sdl2_ttf::{Font, FontStyle};
+
+/// Everything but the text which influences what a rendered string looks
+/// like.
+//? The colour is stored as a tuple so that the key can be hashed no matter
+//? which variant of `Color` was used to build it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct TextStyle {
+	font_path: &'static str,
+	size: i32,
+	color: (u8, u8, u8, u8),
+}
+
+struct CachedText {
+	sprite: Sprite,
+	/// The value of `TextCache::clock` when this entry was last requested.
+	last_used: u64,
+}
+
+/// Keeps the textures of recently rendered strings around so that drawing
+/// the same label on every frame does not rasterize it again.
+///
+/// When more than `capacity` strings are cached, the least recently used one
+/// is evicted. At most `frame_budget` strings may enter the cache during a
+/// single frame; past that point, text is still rendered but not cached, so
+/// that a burst of one-off strings cannot flush the labels which are drawn
+/// every frame.
+pub struct TextCache {
+	//? Strings are grouped by style, so that they can be looked up by `&str`
+	//? without allocating a key on every call.
+	entries: HashMap<TextStyle, HashMap<String, CachedText>>,
+	len: usize,
+	capacity: usize,
+	frame_budget: usize,
+	inserted_this_frame: usize,
+	clock: u64,
+}
+
+impl TextCache {
+	pub fn new(capacity: usize, frame_budget: usize) -> TextCache {
+		TextCache {
+			entries: HashMap::new(),
+			len: 0,
+			capacity: capacity,
+			frame_budget: frame_budget,
+			inserted_this_frame: 0,
+			clock: 0,
+		}
+	}
+
+	/// Resets the per-frame budget. Called by `spawn` before every frame.
+	pub fn begin_frame(&mut self) {
+		self.inserted_this_frame = 0;
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the sprite for `text` rendered with the given style, using
+	/// `font` to rasterize it if it is not cached yet.
+	pub fn get_or_render(&mut self, renderer: &Renderer, font: &Font, text: &str,
+	                     font_path: &'static str, size: i32, color: Color) -> Option<Sprite> {
+		self.clock += 1;
+
+		let style = TextStyle {
+			font_path: font_path,
+			size: size,
+			color: gfx::rgba(color),
+		};
+
+		if let Some(entry) = self.entries.get_mut(&style).and_then(|texts| texts.get_mut(text)) {
+			entry.last_used = self.clock;
+			return Some(entry.sprite.clone());
+		}
+
+		let sprite = match font.render(text, ::sdl2_ttf::blended(color)).ok()
+			.and_then(|s| renderer.create_texture_from_surface(&s).ok())
+			.map(Sprite::new) {
+			Some(sprite) => sprite,
+			None => return None,
+		};
+
+		if self.inserted_this_frame < self.frame_budget {
+			self.inserted_this_frame += 1;
+
+			if self.len >= self.capacity {
+				self.evict_oldest();
+			}
+
+			self.entries.entry(style).or_insert(HashMap::new()).insert(text.to_string(), CachedText {
+				sprite: sprite.clone(),
+				last_used: self.clock,
+			});
+			self.len += 1;
+		}
+
+		Some(sprite)
+	}
+
+	fn evict_oldest(&mut self) {
+		//? A linear scan is fine: the cache holds at most a few hundred
+		//? strings and eviction only happens when a new one is inserted.
+		let oldest = self.entries.iter()
+			.flat_map(|(style, texts)| texts.iter().map(move |(text, entry)| (style, text, entry)))
+			.min_by_key(|&(_, _, entry)| entry.last_used)
+			.map(|(style, text, _)| (*style, text.clone()));
+
+		if let Some((style, text)) = oldest {
+			let now_empty = match self.entries.get_mut(&style) {
+				Some(texts) => {
+					texts.remove(&text);
+					texts.is_empty()
+				},
+				None => false,
+			};
+			if now_empty {
+				self.entries.remove(&style);
+			}
+			self.len -= 1;
+		}
+	}
+}
+
+
+/// The characters which a `DigitAtlas` knows how to draw.
+const DIGIT_CHARS: &'static str = "0123456789+-.,:% ";
+
+/// Renders numeric text, such as a score or a timer, from a single texture
+/// holding every digit. Changing the displayed value does not create any
+/// texture, which makes it cheap to update a HUD on every frame.
+pub struct DigitAtlas {
+	glyphs: HashMap<char, Sprite>,
+	height: f64,
+}
+
+impl DigitAtlas {
+	/// Rasterizes `DIGIT_CHARS` with `font` once, then slices the resulting
+	/// texture into one region per character.
+	pub fn new(renderer: &Renderer, font: &Font, color: Color) -> Option<DigitAtlas> {
+		let strip = match font.render(DIGIT_CHARS, ::sdl2_ttf::blended(color)).ok()
+			.and_then(|s| renderer.create_texture_from_surface(&s).ok())
+			.map(Sprite::new) {
+			Some(strip) => strip,
+			None => return None,
+		};
+
+		let (_, height) = strip.size();
+		let mut glyphs = HashMap::new();
+		let mut left = 0.0;
+
+		for (i, ch) in DIGIT_CHARS.char_indices() {
+			//? The right edge of a glyph is the width of the prefix ending with
+			//? it, which accounts for the spacing chosen by the font.
+			let end = i + ch.len_utf8();
+			let right = match font.size(&DIGIT_CHARS[..end]) {
+				Ok((w, _)) => w as f64,
+				Err(_) => return None,
+			};
+
+			if let Some(region) = strip.region(Rectangle {
+				x: left,
+				y: 0.0,
+				w: right - left,
+				h: height,
+			}) {
+				glyphs.insert(ch, region);
+			}
+
+			left = right;
+		}
+
+		Some(DigitAtlas {
+			glyphs: glyphs,
+			height: height,
+		})
+	}
+
+	/// The size `text` would take on the screen if rendered at scale 1.
+	/// Characters unknown to the atlas are ignored.
+	pub fn measure(&self, text: &str) -> (f64, f64) {
+		let w = text.chars()
+			.filter_map(|ch| self.glyphs.get(&ch))
+			.map(|glyph| glyph.size().0)
+			.fold(0.0, |acc, w| acc + w);
+
+		(w, self.height)
+	}
+
+	/// Draws `text` with its top-left corner at `(x, y)`.
+	pub fn render(&self, renderer: &mut Renderer, text: &str, x: f64, y: f64) {
+		let mut left = x;
+
+		for ch in text.chars() {
+			if let Some(glyph) = self.glyphs.get(&ch) {
+				let (w, h) = glyph.size();
+				renderer.copy_sprite(glyph, Rectangle {
+					x: left,
+					y: y,
+					w: w,
+					h: h,
+				});
+				left += w;
+			}
+		}
+	}
+}
+
+
+/// Side of the square surfaces into which glyphs are packed.
+const ATLAS_PAGE_SIZE: u32 = 512;
+
+/// Where a rasterized glyph lives in a `GlyphAtlas`.
+#[derive(Clone, Copy)]
+struct Glyph {
+	page: usize,
+	rect: Rectangle,
+	/// How far the pen moves after the glyph, which may differ from its
+	/// width, e.g. for italic glyphs which overhang the next one.
+	advance: f64,
+}
+
+/// Packs rectangles into a square of `ATLAS_PAGE_SIZE` pixels.
+//? Glyphs are packed on shelves: rows which are filled from left to right
+//? and are as tall as the tallest glyph they hold.
+struct Shelves {
+	x: u32,
+	y: u32,
+	h: u32,
+}
+
+impl Shelves {
+	fn new() -> Shelves {
+		Shelves {
+			x: 0,
+			y: 0,
+			h: 0,
+		}
+	}
+
+	/// Reserves a `w`x`h` area, or returns `None` if it does not fit.
+	fn allocate(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+		if w > ATLAS_PAGE_SIZE {
+			return None;
+		}
+
+		if self.x + w > ATLAS_PAGE_SIZE {
+			self.x = 0;
+			self.y += self.h;
+			self.h = 0;
+		}
+
+		if self.y + h > ATLAS_PAGE_SIZE {
+			return None;
+		}
+
+		let pos = (self.x, self.y);
+		self.x += w;
+		self.h = ::std::cmp::max(self.h, h);
+		Some(pos)
+	}
+}
+
+/// A surface holding glyphs, along with the texture it was last uploaded to.
+struct AtlasPage {
+	surface: Surface<'static>,
+	sprite: Option<Sprite>,
+	dirty: bool,
+	shelves: Shelves,
+}
+
+impl AtlasPage {
+	fn new() -> Option<AtlasPage> {
+		Surface::new(ATLAS_PAGE_SIZE, ATLAS_PAGE_SIZE, PixelFormatEnum::ARGB8888).ok()
+			.map(|surface| AtlasPage {
+				surface: surface,
+				sprite: None,
+				dirty: false,
+				shelves: Shelves::new(),
+			})
+	}
+
+	/// Reserves a `w`x`h` area of the page, or returns `None` if it is full.
+	fn allocate(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+		self.shelves.allocate(w, h)
+	}
+}
+
+/// Rasterizes the glyphs of a TrueType font on demand and packs them into
+/// shared textures. Glyphs are rendered in white, so that they can be drawn
+/// in any colour with `CopySprite::copy_sprite_tinted`.
+pub struct GlyphAtlas {
+	font: Font,
+	pages: Vec<AtlasPage>,
+	glyphs: HashMap<char, Glyph>,
+	kerning: HashMap<(char, char), f64>,
+}
+
+impl GlyphAtlas {
+	pub fn new(font_path: &str, size: i32, style: FontStyle) -> Option<GlyphAtlas> {
+		Font::from_file(Path::new(font_path), size).ok()
+			.map(|mut font| {
+				font.set_style(style);
+				font
+			})
+			.map(|font| GlyphAtlas {
+				font: font,
+				pages: Vec::new(),
+				glyphs: HashMap::new(),
+				kerning: HashMap::new(),
+			})
+	}
+
+	/// The distance between the baselines of two consecutive lines.
+	pub fn line_height(&self) -> f64 {
+		self.font.line_skip() as f64
+	}
+
+	/// Makes sure that `ch` was rasterized into one of the pages. Returns
+	/// `None` if the font cannot render it.
+	fn rasterize(&mut self, ch: char) -> Option<Glyph> {
+		if let Some(glyph) = self.glyphs.get(&ch) {
+			return Some(*glyph);
+		}
+
+		let white = Color::RGBA(255, 255, 255, 255);
+		let mut surface = match self.font.render(ch, ::sdl2_ttf::blended(white)) {
+			Ok(surface) => surface,
+			Err(_) => return None,
+		};
+		let (w, h) = (surface.width(), surface.height());
+		//? Such a glyph would not fit in a new page either.
+		if w > ATLAS_PAGE_SIZE || h > ATLAS_PAGE_SIZE {
+			return None;
+		}
+
+		//? Try the last page first, and open a new one if it is full.
+		let mut pos = self.pages.last_mut().and_then(|page| page.allocate(w, h));
+		if pos.is_none() {
+			let mut page = match AtlasPage::new() {
+				Some(page) => page,
+				None => return None,
+			};
+			pos = page.allocate(w, h);
+			self.pages.push(page);
+		}
+		let (x, y) = match pos {
+			Some(pos) => pos,
+			None => return None,
+		};
+
+		let page_index = self.pages.len() - 1;
+		let page = &mut self.pages[page_index];
+
+		//? Copy the glyph's alpha channel as-is instead of blending it with
+		//? the (transparent) content of the page.
+		surface.set_blend_mode(BlendMode::None).ok();
+		if surface.blit(None, &mut page.surface, SdlRect::new(x as i32, y as i32, w, h).unwrap()).is_err() {
+			return None;
+		}
+		page.dirty = true;
+
+		let glyph = Glyph {
+			page: page_index,
+			rect: Rectangle {
+				x: x as f64,
+				y: y as f64,
+				w: w as f64,
+				h: h as f64,
+			},
+			advance: self.font.metrics_of_char(ch)
+				.map(|metrics| metrics.advance as f64)
+				.unwrap_or(w as f64),
+		};
+		self.glyphs.insert(ch, glyph);
+		Some(glyph)
+	}
+
+	/// How much closer (negative) or further (positive) `right` should be
+	/// drawn from `left` than their advances suggest.
+	pub fn kerning(&mut self, left: char, right: char) -> f64 {
+		if let Some(offset) = self.kerning.get(&(left, right)) {
+			return *offset;
+		}
+
+		//? SDL_ttf applies kerning when rendering a string, so the kerning of
+		//? a pair is whatever the pair measures on top of its two glyphs.
+		let offset = {
+			let pair: String = vec![left, right].into_iter().collect();
+			let width = |text: &str| self.font.size(text).map(|(w, _)| w as f64).unwrap_or(0.0);
+			width(&pair) - width(&left.to_string()) - width(&right.to_string())
+		};
+
+		self.kerning.insert((left, right), offset);
+		offset
+	}
+
+	/// Uploads the pages which received new glyphs since the last call.
+	fn upload(&mut self, renderer: &Renderer) {
+		for page in self.pages.iter_mut().filter(|page| page.dirty) {
+			if let Ok(texture) = renderer.create_texture_from_surface(&page.surface) {
+				//? Sprites which were handed out before keep the old texture
+				//? alive, and it still holds every glyph they refer to.
+				page.sprite = Some(Sprite::new(texture));
+				page.dirty = false;
+			}
+		}
+	}
+
+	/// How far the pen moves after `ch`, or `None` if the font cannot
+	/// render it.
+	pub fn advance(&mut self, ch: char) -> Option<f64> {
+		self.rasterize(ch).map(|glyph| glyph.advance)
+	}
+
+	fn sprite(&self, glyph: Glyph) -> Option<Sprite> {
+		self.pages[glyph.page].sprite.as_ref()
+			.and_then(|sprite| sprite.region(glyph.rect))
+	}
+
+	/// Rasterizes every character of `text` without uploading anything, so
+	/// that the following calls to `glyph_sprite` upload the pages once.
+	pub fn prepare(&mut self, text: &str) {
+		for ch in text.chars() {
+			self.rasterize(ch);
+		}
+	}
+
+	/// Returns the sprite of `ch`, rasterizing and uploading it if needed.
+	/// Prefer `TextLayout` when drawing whole strings, or call `prepare`
+	/// first, so that the atlas is uploaded once for all of the new glyphs.
+	pub fn glyph_sprite(&mut self, renderer: &Renderer, ch: char) -> Option<Sprite> {
+		match self.rasterize(ch) {
+			Some(glyph) => {
+				self.upload(renderer);
+				self.sprite(glyph)
+			},
+			None => None,
+		}
+	}
+}
+
+/// Every `GlyphAtlas` created so far, by font, size and style.
+pub struct AtlasCache {
+	atlases: HashMap<(&'static str, i32, i32), GlyphAtlas>,
+}
+
+impl AtlasCache {
+	pub fn new() -> AtlasCache {
+		AtlasCache {
+			atlases: HashMap::new(),
+		}
+	}
+
+	/// Returns the atlas for the given font, creating it on first use.
+	pub fn get(&mut self, font_path: &'static str, size: i32, style: FontStyle) -> Option<&mut GlyphAtlas> {
+		use ::std::collections::hash_map::Entry;
+
+		match self.atlases.entry((font_path, size, style.bits())) {
+			Entry::Occupied(entry) => Some(entry.into_mut()),
+			Entry::Vacant(entry) => GlyphAtlas::new(font_path, size, style)
+				.map(|atlas| entry.insert(atlas)),
+		}
+	}
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+	Left,
+	Center,
+	Right,
+}
+
+/// How a `TextLayout` should arrange its text.
+#[derive(Clone, Copy, Debug)]
+pub struct TextOptions {
+	pub align: Align,
+	/// Lines longer than this are wrapped at word boundaries, or between
+	/// characters for words which do not fit on a line by themselves.
+	pub max_width: Option<f64>,
+	/// Multiplies the line height recommended by the font.
+	pub line_spacing: f64,
+}
+
+impl Default for TextOptions {
+	fn default() -> TextOptions {
+		TextOptions {
+			align: Align::Left,
+			max_width: None,
+			line_spacing: 1.0,
+		}
+	}
+}
+
+impl TextOptions {
+	pub fn aligned(align: Align, width: f64) -> TextOptions {
+		TextOptions {
+			align: align,
+			max_width: Some(width),
+			.. TextOptions::default()
+		}
+	}
+}
+
+/// A glyph as handed out by a `GlyphSource`, ready to be positioned.
+#[derive(Clone)]
+pub struct GlyphQuad {
+	/// `None` for glyphs which only move the pen, such as spaces.
+	pub sprite: Option<Sprite>,
+	/// Where the sprite is drawn relative to the pen position and to the top
+	/// of the line, and at which size.
+	pub dest: Rectangle,
+	/// How far the pen moves after this glyph.
+	pub advance: f64,
+}
+
+/// Anything which can provide glyphs to a `TextLayout`, so that the same
+/// layout code serves TrueType and bitmap fonts.
+pub trait GlyphSource {
+	/// The distance between the tops of two consecutive lines.
+	fn line_height(&self) -> f64;
+
+	/// How far the pen moves after `ch`, or `None` if the font lacks it.
+	/// Unlike `glyph`, this must not require a renderer, so that text can be
+	/// measured before anything is uploaded.
+	fn advance(&mut self, ch: char) -> Option<f64>;
+
+	fn glyph(&mut self, renderer: &Renderer, ch: char) -> Option<GlyphQuad>;
+
+	/// Adjustment of the distance between `left` and `right`.
+	fn kerning(&mut self, left: char, right: char) -> f64;
+}
+
+impl GlyphSource for GlyphAtlas {
+	fn line_height(&self) -> f64 {
+		GlyphAtlas::line_height(self)
+	}
+
+	fn advance(&mut self, ch: char) -> Option<f64> {
+		GlyphAtlas::advance(self, ch)
+	}
+
+	fn glyph(&mut self, renderer: &Renderer, ch: char) -> Option<GlyphQuad> {
+		let advance = match GlyphAtlas::advance(self, ch) {
+			Some(advance) => advance,
+			None => return None,
+		};
+		self.glyph_sprite(renderer, ch).map(|sprite| {
+			let (w, h) = sprite.size();
+			GlyphQuad {
+				sprite: Some(sprite),
+				dest: Rectangle { x: 0.0, y: 0.0, w: w, h: h },
+				advance: advance,
+			}
+		})
+	}
+
+	fn kerning(&mut self, left: char, right: char) -> f64 {
+		GlyphAtlas::kerning(self, left, right)
+	}
+}
+
+/// A glyph positioned relative to the top-left corner of its layout.
+#[derive(Clone)]
+pub struct PositionedGlyph {
+	pub ch: char,
+	pub sprite: Sprite,
+	pub dest: Rectangle,
+}
+
+/// Text which was broken into lines and positioned glyph by glyph. It may be
+/// measured before it is drawn, for example to center it in a box.
+#[derive(Clone)]
+pub struct TextLayout {
+	pub glyphs: Vec<PositionedGlyph>,
+	pub w: f64,
+	pub h: f64,
+}
+
+impl TextLayout {
+	/// Breaks `text` into lines according to `options` and places its glyphs.
+	/// When `max_width` is set, lines are aligned inside of that width;
+	/// otherwise, they are aligned relative to the longest line.
+	pub fn new<S: GlyphSource>(source: &mut S, renderer: &Renderer, text: &str, options: &TextOptions) -> TextLayout {
+		//? Wrapping measures every glyph, so that a `GlyphAtlas` rasterizes
+		//? all of them before the first one is requested, and uploads its
+		//? pages once.
+		let lines = wrap(source, text, options.max_width);
+
+		let line_height = source.line_height() * options.line_spacing;
+		let widest = lines.iter().map(|line| line.1).fold(0.0, f64::max);
+		let box_w = options.max_width.unwrap_or(widest);
+
+		let mut glyphs = Vec::new();
+		for (row, &(ref chars, width)) in lines.iter().enumerate() {
+			let mut pen_x = match options.align {
+				Align::Left => 0.0,
+				Align::Center => (box_w - width) / 2.0,
+				Align::Right => box_w - width,
+			};
+			let mut prev = None;
+
+			for &ch in chars {
+				let quad = match source.glyph(renderer, ch) {
+					Some(quad) => quad,
+					None => continue,
+				};
+				if let Some(prev) = prev {
+					pen_x += source.kerning(prev, ch);
+				}
+				if let Some(sprite) = quad.sprite {
+					glyphs.push(PositionedGlyph {
+						ch: ch,
+						sprite: sprite,
+						dest: Rectangle {
+							x: pen_x + quad.dest.x,
+							y: row as f64 * line_height + quad.dest.y,
+							.. quad.dest
+						},
+					});
+				}
+				pen_x += quad.advance;
+				prev = Some(ch);
+			}
+		}
+
+		TextLayout {
+			glyphs: glyphs,
+			w: box_w,
+			h: lines.len() as f64 * line_height,
+		}
+	}
+
+	pub fn size(&self) -> (f64, f64) {
+		(self.w, self.h)
+	}
+}
+
+/// Measures `chars` as they would be laid out on a single line.
+fn measure<S: GlyphSource>(source: &mut S, chars: &[char]) -> f64 {
+	let mut width = 0.0;
+	let mut prev = None;
+
+	for &ch in chars {
+		if let Some(advance) = source.advance(ch) {
+			if let Some(prev) = prev {
+				width += source.kerning(prev, ch);
+			}
+			width += advance;
+			prev = Some(ch);
+		}
+	}
+
+	width
+}
+
+/// Splits `text` into lines no wider than `max_width`, returning the
+/// characters of every line along with its width.
+fn wrap<S: GlyphSource>(source: &mut S, text: &str, max_width: Option<f64>) -> Vec<(Vec<char>, f64)> {
+	let mut lines = Vec::new();
+
+	for paragraph in text.split('\n') {
+		let max_width = match max_width {
+			Some(w) => w,
+			None => {
+				let chars: Vec<char> = paragraph.chars().collect();
+				let width = measure(source, &chars);
+				lines.push((chars, width));
+				continue;
+			}
+		};
+
+		let mut line: Vec<char> = Vec::new();
+
+		for word in paragraph.split(' ') {
+			let mut candidate = line.clone();
+			if !candidate.is_empty() {
+				candidate.push(' ');
+			}
+			candidate.extend(word.chars());
+
+			if measure(source, &candidate) <= max_width {
+				line = candidate;
+				continue;
+			}
+
+			//? The word does not fit: flush the current line and start a new
+			//? one, splitting the word itself if it is too long.
+			if !line.is_empty() {
+				let width = measure(source, &line);
+				lines.push((line, width));
+			}
+			line = Vec::new();
+
+			for ch in word.chars() {
+				line.push(ch);
+				if line.len() > 1 && measure(source, &line) > max_width {
+					line.pop();
+					let width = measure(source, &line);
+					lines.push((line, width));
+					line = vec![ch];
+				}
+			}
+		}
+
+		let width = measure(source, &line);
+		lines.push((line, width));
+	}
+
+	lines
+}
+
+pub trait CopyText {
+	/// Draws `layout` with its top-left corner at `(x, y)`.
+	fn copy_text(&mut self, layout: &TextLayout, x: f64, y: f64, color: Color);
+}
+
+impl<'window> CopyText for Renderer<'window> {
+	fn copy_text(&mut self, layout: &TextLayout, x: f64, y: f64, color: Color) {
+		for glyph in &layout.glyphs {
+			self.copy_sprite_tinted(&glyph.sprite, Rectangle {
+				x: x + glyph.dest.x,
+				y: y + glyph.dest.y,
+				.. glyph.dest
+			}, color);
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{wrap, GlyphQuad, GlyphSource, Shelves, ATLAS_PAGE_SIZE};
+
+	use ::sdl2::render::Renderer;
+
+	/// Every glyph is 10 pixels wide, except the space which is 5, and `A`
+	/// is drawn 2 pixels closer to a following `V`.
+	struct Fixed;
+
+	impl GlyphSource for Fixed {
+		fn line_height(&self) -> f64 {
+			20.0
+		}
+
+		fn advance(&mut self, ch: char) -> Option<f64> {
+			match ch {
+				' ' => Some(5.0),
+				'\t' => None,
+				_ => Some(10.0),
+			}
+		}
+
+		fn glyph(&mut self, _: &Renderer, _: char) -> Option<GlyphQuad> {
+			None
+		}
+
+		fn kerning(&mut self, left: char, right: char) -> f64 {
+			if (left, right) == ('A', 'V') { -2.0 } else { 0.0 }
+		}
+	}
+
+	fn lines(text: &str, max_width: Option<f64>) -> Vec<(String, f64)> {
+		wrap(&mut Fixed, text, max_width).into_iter()
+			.map(|(chars, width)| (chars.into_iter().collect(), width))
+			.collect()
+	}
+
+	#[test]
+	fn wrap_on_spaces() {
+		assert_eq!(lines("ab cd ef", Some(55.0)), vec![
+			("ab cd".to_string(), 45.0),
+			("ef".to_string(), 20.0),
+		]);
+		//? Without a width, only line breaks split the text.
+		assert_eq!(lines("ab cd\nef", None), vec![
+			("ab cd".to_string(), 45.0),
+			("ef".to_string(), 20.0),
+		]);
+	}
+
+	#[test]
+	fn wrap_splits_words_wider_than_a_line() {
+		assert_eq!(lines("abcdefg", Some(30.0)), vec![
+			("abc".to_string(), 30.0),
+			("def".to_string(), 30.0),
+			("g".to_string(), 10.0),
+		]);
+		//? A single glyph is kept even if it does not fit.
+		assert_eq!(lines("ab", Some(5.0)), vec![
+			("a".to_string(), 10.0),
+			("b".to_string(), 10.0),
+		]);
+	}
+
+	#[test]
+	fn wrap_measures_with_kerning_and_skips_missing_glyphs() {
+		assert_eq!(lines("AV", None), vec![("AV".to_string(), 18.0)]);
+		assert_eq!(lines("a\tb", None), vec![("a\tb".to_string(), 20.0)]);
+	}
+
+	#[test]
+	fn shelves_fill_rows_from_left_to_right() {
+		let mut shelves = Shelves::new();
+		assert_eq!(shelves.allocate(200, 30), Some((0, 0)));
+		assert_eq!(shelves.allocate(200, 50), Some((200, 0)));
+		//? The next row starts below the tallest glyph of the first one.
+		assert_eq!(shelves.allocate(200, 10), Some((0, 50)));
+		assert_eq!(shelves.allocate(ATLAS_PAGE_SIZE, 10), Some((0, 60)));
+	}
+
+	#[test]
+	fn shelves_refuse_what_does_not_fit() {
+		let mut shelves = Shelves::new();
+		assert_eq!(shelves.allocate(ATLAS_PAGE_SIZE + 1, 1), None);
+		assert_eq!(shelves.allocate(1, ATLAS_PAGE_SIZE + 1), None);
+		//? Refusing an oversize glyph does not waste a row.
+		assert_eq!(shelves.allocate(10, ATLAS_PAGE_SIZE), Some((0, 0)));
+		assert_eq!(shelves.allocate(ATLAS_PAGE_SIZE, 1), None);
+	}
+}
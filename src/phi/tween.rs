@@ -0,0 +1,676 @@
+use ::phi::Phi;
+use ::phi::clock::Layer;
+use ::phi::data::Rectangle;
+use ::phi::gfx::rgba;
+
+use ::std::cell::Cell;
+use ::std::f64::consts::PI;
+use ::std::rc::Rc;
+
+use ::sdl2::pixels::Color;
+
+/// The shape of an easing function, which `Ease` applies at the start, the
+/// end or both ends of a tween.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Curve {
+	Quad,
+	Cubic,
+	Sine,
+	/// Overshoots back and forth, like a spring.
+	Elastic,
+	/// Bounces against the end, like a dropped ball.
+	Bounce,
+	/// Goes slightly the wrong way before going the right one.
+	Back,
+}
+
+impl Curve {
+	/// The curve eased in, for `t` from 0 to 1.
+	fn ease_in(self, t: f64) -> f64 {
+		match self {
+			Curve::Quad => t * t,
+			Curve::Cubic => t * t * t,
+			Curve::Sine => 1.0 - (t * PI / 2.0).cos(),
+			Curve::Elastic =>
+				if t <= 0.0 || t >= 1.0 { t }
+				else { -(2.0f64).powf(10.0 * t - 10.0) * ((10.0 * t - 10.75) * 2.0 * PI / 3.0).sin() },
+			Curve::Bounce => 1.0 - bounce_out(1.0 - t),
+			Curve::Back => {
+				const OVERSHOOT: f64 = 1.70158;
+				(OVERSHOOT + 1.0) * t * t * t - OVERSHOOT * t * t
+			},
+		}
+	}
+}
+
+fn bounce_out(t: f64) -> f64 {
+	const N: f64 = 7.5625;
+	const D: f64 = 2.75;
+
+	if t < 1.0 / D {
+		N * t * t
+	} else if t < 2.0 / D {
+		let t = t - 1.5 / D;
+		N * t * t + 0.75
+	} else if t < 2.5 / D {
+		let t = t - 2.25 / D;
+		N * t * t + 0.9375
+	} else {
+		let t = t - 2.625 / D;
+		N * t * t + 0.984375
+	}
+}
+
+/// How a tween goes from its start to its end, e.g. `Ease::Out(Curve::Back)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ease {
+	Linear,
+	/// Starts slowly.
+	In(Curve),
+	/// Ends slowly.
+	Out(Curve),
+	/// Starts and ends slowly.
+	InOut(Curve),
+}
+
+impl Ease {
+	/// How far along the tween is, usually from 0 to 1, when `t` of its
+	/// duration has passed.
+	pub fn apply(self, t: f64) -> f64 {
+		let t = t.max(0.0).min(1.0);
+		match self {
+			Ease::Linear => t,
+			Ease::In(curve) => curve.ease_in(t),
+			Ease::Out(curve) => 1.0 - curve.ease_in(1.0 - t),
+			Ease::InOut(curve) =>
+				if t < 0.5 { curve.ease_in(2.0 * t) / 2.0 }
+				else { 1.0 - curve.ease_in(2.0 - 2.0 * t) / 2.0 },
+		}
+	}
+}
+
+/// A value which can be tweened. `t` may leave the 0 to 1 range for the
+/// curves which overshoot.
+pub trait Lerp: Copy {
+	fn lerp(self, to: Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+	fn lerp(self, to: f64, t: f64) -> f64 {
+		self + (to - self) * t
+	}
+}
+
+/// Points and displacements, as `(x, y)`.
+impl Lerp for (f64, f64) {
+	fn lerp(self, to: (f64, f64), t: f64) -> (f64, f64) {
+		(self.0.lerp(to.0, t), self.1.lerp(to.1, t))
+	}
+}
+
+impl Lerp for Color {
+	fn lerp(self, to: Color, t: f64) -> Color {
+		let channel = |from: u8, to: u8| (from as f64).lerp(to as f64, t).round().max(0.0).min(255.0) as u8;
+		let ((r1, g1, b1, a1), (r2, g2, b2, a2)) = (rgba(self), rgba(to));
+		Color::RGBA(channel(r1, r2), channel(g1, g2), channel(b1, b2), channel(a1, a2))
+	}
+}
+
+impl Lerp for Rectangle {
+	fn lerp(self, to: Rectangle, t: f64) -> Rectangle {
+		Rectangle {
+			x: self.x.lerp(to.x, t),
+			y: self.y.lerp(to.y, t),
+			//? Overshooting curves must not make the size negative.
+			w: self.w.lerp(to.w, t).max(0.0),
+			h: self.h.lerp(to.h, t).max(0.0),
+		}
+	}
+}
+
+/// A value which tweens change. It is shared between the view which reads
+/// it and the tweens which write it, so cloning it gives another handle on
+/// the same value.
+#[derive(Clone)]
+pub struct Tweened<T: Copy>(Rc<Cell<T>>);
+
+impl<T: Copy> Tweened<T> {
+	pub fn new(value: T) -> Tweened<T> {
+		Tweened(Rc::new(Cell::new(value)))
+	}
+
+	pub fn get(&self) -> T {
+		self.0.get()
+	}
+
+	pub fn set(&self, value: T) {
+		self.0.set(value)
+	}
+}
+
+/// Something which plays over time: a tween, or a group of them.
+///
+/// Views own their animations, and advance them every frame by the time of
+/// the layer they belong to, e.g. `phi.clock.delta(Layer::Ui)`.
+pub trait Animation {
+	/// Plays `dt` more seconds. Returns the part of `dt` which was left once
+	/// the animation finished, so that the next one can start with it, or 0
+	/// while it is still playing.
+	fn advance(&mut self, dt: f64) -> f64;
+
+	fn is_finished(&self) -> bool;
+
+	/// Goes back to the start, to be played again.
+	fn restart(&mut self);
+}
+
+/// Changes a `Tweened` value to `to` over `duration` seconds.
+pub struct Tween<T: Lerp> {
+	target: Tweened<T>,
+	/// Where the tween starts, or `None` to start from the value of the
+	/// target when the tween starts, so that tweens can be chained.
+	from: Option<T>,
+	start: Option<T>,
+	to: T,
+	duration: f64,
+	ease: Ease,
+	elapsed: f64,
+}
+
+impl<T: Lerp> Tween<T> {
+	pub fn to(target: &Tweened<T>, to: T, duration: f64) -> Tween<T> {
+		Tween {
+			target: target.clone(),
+			from: None,
+			start: None,
+			to: to,
+			duration: duration,
+			ease: Ease::Linear,
+			elapsed: 0.0,
+		}
+	}
+
+	pub fn from_to(target: &Tweened<T>, from: T, to: T, duration: f64) -> Tween<T> {
+		Tween {
+			from: Some(from),
+			.. Tween::to(target, to, duration)
+		}
+	}
+
+	pub fn ease(mut self, ease: Ease) -> Tween<T> {
+		self.ease = ease;
+		self
+	}
+}
+
+impl<T: Lerp> Animation for Tween<T> {
+	fn advance(&mut self, dt: f64) -> f64 {
+		if self.is_finished() && self.start.is_some() {
+			return dt;
+		}
+
+		let start = match self.start {
+			Some(start) => start,
+			None => {
+				let start = self.from.unwrap_or(self.target.get());
+				self.start = Some(start);
+				start
+			},
+		};
+
+		self.elapsed += dt;
+		let leftover = (self.elapsed - self.duration).max(0.0);
+		self.elapsed = self.elapsed.min(self.duration);
+
+		let t = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+		self.target.set(start.lerp(self.to, self.ease.apply(t)));
+		leftover
+	}
+
+	fn is_finished(&self) -> bool {
+		self.elapsed >= self.duration
+	}
+
+	fn restart(&mut self) {
+		self.elapsed = 0.0;
+		self.start = None;
+	}
+}
+
+/// Does nothing for a while, e.g. between the tweens of a `Sequence`.
+pub struct Wait {
+	duration: f64,
+	elapsed: f64,
+}
+
+impl Wait {
+	pub fn new(duration: f64) -> Wait {
+		Wait {
+			duration: duration,
+			elapsed: 0.0,
+		}
+	}
+}
+
+impl Animation for Wait {
+	fn advance(&mut self, dt: f64) -> f64 {
+		self.elapsed += dt;
+		let leftover = (self.elapsed - self.duration).max(0.0);
+		self.elapsed = self.elapsed.min(self.duration);
+		leftover
+	}
+
+	fn is_finished(&self) -> bool {
+		self.elapsed >= self.duration
+	}
+
+	fn restart(&mut self) {
+		self.elapsed = 0.0;
+	}
+}
+
+/// Plays animations one after the other.
+pub struct Sequence {
+	steps: Vec<Box<Animation>>,
+	current: usize,
+}
+
+impl Sequence {
+	pub fn new(steps: Vec<Box<Animation>>) -> Sequence {
+		Sequence {
+			steps: steps,
+			current: 0,
+		}
+	}
+}
+
+impl Animation for Sequence {
+	fn advance(&mut self, dt: f64) -> f64 {
+		let mut dt = dt;
+		while self.current < self.steps.len() {
+			dt = self.steps[self.current].advance(dt);
+			if !self.steps[self.current].is_finished() {
+				return 0.0;
+			}
+			self.current += 1;
+		}
+		dt
+	}
+
+	fn is_finished(&self) -> bool {
+		self.current >= self.steps.len()
+	}
+
+	fn restart(&mut self) {
+		for step in &mut self.steps {
+			step.restart();
+		}
+		self.current = 0;
+	}
+}
+
+/// Plays animations at the same time, until they have all finished.
+pub struct Parallel {
+	animations: Vec<Box<Animation>>,
+}
+
+impl Parallel {
+	pub fn new(animations: Vec<Box<Animation>>) -> Parallel {
+		Parallel {
+			animations: animations,
+		}
+	}
+}
+
+impl Animation for Parallel {
+	fn advance(&mut self, dt: f64) -> f64 {
+		let leftover = self.animations.iter_mut()
+			.map(|animation| animation.advance(dt))
+			.fold(dt, f64::min);
+		if self.is_finished() { leftover } else { 0.0 }
+	}
+
+	fn is_finished(&self) -> bool {
+		self.animations.iter().all(|animation| animation.is_finished())
+	}
+
+	fn restart(&mut self) {
+		for animation in &mut self.animations {
+			animation.restart();
+		}
+	}
+}
+
+/// Plays an animation a number of times, or forever.
+pub struct Repeat {
+	animation: Box<Animation>,
+	times: Option<u32>,
+	done: u32,
+}
+
+impl Repeat {
+	pub fn times(animation: Box<Animation>, times: u32) -> Repeat {
+		Repeat {
+			animation: animation,
+			times: Some(times),
+			done: 0,
+		}
+	}
+
+	pub fn forever(animation: Box<Animation>) -> Repeat {
+		Repeat {
+			animation: animation,
+			times: None,
+			done: 0,
+		}
+	}
+}
+
+impl Animation for Repeat {
+	fn advance(&mut self, dt: f64) -> f64 {
+		let mut dt = dt;
+		loop {
+			if self.is_finished() {
+				return dt;
+			}
+
+			let before = dt;
+			dt = self.animation.advance(dt);
+			if !self.animation.is_finished() {
+				return 0.0;
+			}
+
+			self.done += 1;
+			if self.is_finished() {
+				return dt;
+			}
+			self.animation.restart();
+
+			//? An animation which takes no time would repeat forever within
+			//? a single frame.
+			if dt >= before {
+				return 0.0;
+			}
+		}
+	}
+
+	fn is_finished(&self) -> bool {
+		self.times.map_or(false, |times| self.done >= times)
+	}
+
+	fn restart(&mut self) {
+		self.animation.restart();
+		self.done = 0;
+	}
+}
+
+/// Identifies a task of the `Scheduler`, to cancel it.
+pub type TaskId = u64;
+
+struct Task {
+	id: TaskId,
+	/// The layer whose time the task waits for.
+	layer: Layer,
+	/// The time left before the task runs.
+	due: f64,
+	/// How often the task runs again, if it repeats.
+	interval: Option<f64>,
+	callback: Box<FnMut(&mut Phi)>,
+}
+
+/// Runs callbacks after some time, once or repeatedly, as time passes for
+/// their layer: a callback of `Layer::Gameplay` waits while the game is
+/// paused or slowed down.
+///
+/// Whoever schedules a repeating task cancels it when it is no longer
+/// needed, such as when its view is left.
+pub struct Scheduler {
+	tasks: Vec<Task>,
+	next_id: TaskId,
+	/// The tasks cancelled while the callbacks were running.
+	cancelled: Vec<TaskId>,
+}
+
+impl Scheduler {
+	pub fn new() -> Scheduler {
+		Scheduler {
+			tasks: Vec::new(),
+			next_id: 0,
+			cancelled: Vec::new(),
+		}
+	}
+
+	fn schedule(&mut self, layer: Layer, due: f64, interval: Option<f64>, callback: Box<FnMut(&mut Phi)>) -> TaskId {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.tasks.push(Task {
+			id: id,
+			layer: layer,
+			due: due,
+			interval: interval,
+			callback: callback,
+		});
+		id
+	}
+
+	/// Runs `callback` once, after `delay` seconds.
+	pub fn after(&mut self, layer: Layer, delay: f64, callback: Box<FnMut(&mut Phi)>) -> TaskId {
+		self.schedule(layer, delay, None, callback)
+	}
+
+	/// Runs `callback` every `interval` seconds, starting in `interval`
+	/// seconds. Returns `None`, scheduling nothing, if `interval` is not
+	/// positive.
+	///
+	/// It runs at most once per frame: the periods missed during a long
+	/// frame, such as a hitch, are dropped rather than run in a burst over
+	/// the next frames.
+	pub fn every(&mut self, layer: Layer, interval: f64, callback: Box<FnMut(&mut Phi)>) -> Option<TaskId> {
+		if interval > 0.0 {
+			Some(self.schedule(layer, interval, Some(interval), callback))
+		} else {
+			None
+		}
+	}
+
+	pub fn cancel(&mut self, id: TaskId) {
+		self.tasks.retain(|task| task.id != id);
+		self.cancelled.push(id);
+	}
+
+	/// Lets `delta(layer)` seconds pass for the tasks of every layer, and
+	/// takes out the tasks which are due, in the order they were scheduled.
+	fn take_due(&mut self, delta: &Fn(Layer) -> f64) -> Vec<Task> {
+		self.cancelled.clear();
+
+		let tasks = ::std::mem::replace(&mut self.tasks, Vec::new());
+		let mut due = Vec::new();
+		for mut task in tasks {
+			task.due -= delta(task.layer);
+			if task.due > 0.0 { self.tasks.push(task) } else { due.push(task) }
+		}
+		due
+	}
+
+	/// Puts back a task which just ran, if it repeats and was not cancelled
+	/// in the meantime.
+	fn ran(&mut self, mut task: Task) {
+		if let Some(interval) = task.interval {
+			if !self.cancelled.contains(&task.id) {
+				task.due = task.due.max(0.0) + interval;
+				self.tasks.push(task);
+			}
+		}
+	}
+}
+
+/// Runs the tasks of `phi.scheduler` which are due. Called by `spawn` once
+/// the duration of the frame is known.
+pub fn update(phi: &mut Phi) {
+	//? The tasks are taken out of the scheduler, so that their callbacks
+	//? can be given `phi`, and schedule or cancel tasks themselves.
+	let due = {
+		let clock = &phi.clock;
+		phi.scheduler.take_due(&|layer| clock.delta(layer))
+	};
+
+	for mut task in due {
+		//? An earlier callback may have cancelled it.
+		if phi.scheduler.cancelled.contains(&task.id) {
+			continue;
+		}
+
+		(task.callback)(phi);
+		phi.scheduler.ran(task);
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{Animation, Curve, Ease, Parallel, Repeat, Scheduler, Sequence, Tween, Tweened, Wait};
+	use ::phi::clock::Layer;
+
+	fn close(a: f64, b: f64) -> bool {
+		(a - b).abs() < 1e-9
+	}
+
+	const CURVES: [Curve; 6] = [Curve::Quad, Curve::Cubic, Curve::Sine, Curve::Elastic, Curve::Bounce, Curve::Back];
+
+	#[test]
+	fn every_ease_starts_at_0_and_ends_at_1() {
+		for &curve in CURVES.iter() {
+			for &ease in &[Ease::In(curve), Ease::Out(curve), Ease::InOut(curve)] {
+				assert!(close(ease.apply(0.0), 0.0), "{:?} does not start at 0", ease);
+				assert!(close(ease.apply(1.0), 1.0), "{:?} does not end at 1", ease);
+				//? Outside of the tween, it is clamped.
+				assert!(close(ease.apply(-1.0), 0.0) && close(ease.apply(2.0), 1.0));
+			}
+		}
+	}
+
+	#[test]
+	fn ease_shapes() {
+		assert!(close(Ease::Linear.apply(0.25), 0.25));
+		assert!(close(Ease::In(Curve::Quad).apply(0.5), 0.25));
+		assert!(close(Ease::Out(Curve::Quad).apply(0.5), 0.75));
+		assert!(close(Ease::InOut(Curve::Cubic).apply(0.5), 0.5));
+		//? Back goes the wrong way first.
+		assert!(Ease::In(Curve::Back).apply(0.2) < 0.0);
+	}
+
+	#[test]
+	fn tween_returns_the_leftover_time() {
+		let value = Tweened::new(0.0);
+		let mut tween = Tween::to(&value, 10.0, 2.0);
+
+		assert!(close(tween.advance(1.0), 0.0));
+		assert!(close(value.get(), 5.0));
+		assert!(!tween.is_finished());
+
+		assert!(close(tween.advance(1.5), 0.5));
+		assert!(close(value.get(), 10.0));
+		assert!(tween.is_finished());
+	}
+
+	#[test]
+	fn tween_starts_from_the_value_it_finds() {
+		let value = Tweened::new(0.0);
+		let mut sequence = Sequence::new(vec![
+			Box::new(Tween::to(&value, 4.0, 1.0)),
+			Box::new(Wait::new(1.0)),
+			Box::new(Tween::to(&value, 0.0, 1.0).ease(Ease::In(Curve::Quad))),
+		]);
+
+		assert!(close(sequence.advance(2.5), 0.0));
+		assert!(close(value.get(), 3.0));
+
+		sequence.restart();
+		value.set(8.0);
+		assert!(close(sequence.advance(0.5), 0.0));
+		assert!(close(value.get(), 6.0));
+	}
+
+	#[test]
+	fn parallel_lasts_as_long_as_its_longest_animation() {
+		let (a, b) = (Tweened::new(0.0), Tweened::new(0.0));
+		let mut parallel = Parallel::new(vec![
+			Box::new(Tween::to(&a, 1.0, 1.0)),
+			Box::new(Tween::to(&b, 1.0, 3.0)),
+		]);
+
+		assert!(close(parallel.advance(2.0), 0.0));
+		assert!(close(a.get(), 1.0));
+		assert!(close(parallel.advance(2.0), 1.0));
+		assert!(parallel.is_finished());
+	}
+
+	#[test]
+	fn repeat_plays_its_animation_a_number_of_times() {
+		let value = Tweened::new(0.0);
+		let mut repeat = Repeat::times(Box::new(Tween::from_to(&value, 0.0, 1.0, 1.0)), 3);
+
+		//? Two repetitions and a half within a single frame.
+		assert!(close(repeat.advance(2.5), 0.0));
+		assert!(close(value.get(), 0.5));
+		assert!(close(repeat.advance(1.0), 0.5));
+		assert!(repeat.is_finished());
+	}
+
+	#[test]
+	fn repeat_of_an_instant_animation_does_not_hang() {
+		let mut repeat = Repeat::forever(Box::new(Wait::new(0.0)));
+		assert!(close(repeat.advance(1.0), 0.0));
+		assert!(!repeat.is_finished());
+	}
+
+	#[test]
+	fn scheduler_waits_for_the_layer_of_every_task() {
+		let mut scheduler = Scheduler::new();
+		let once = scheduler.after(Layer::Gameplay, 1.0, Box::new(|_| {}));
+		let every = scheduler.every(Layer::Ui, 0.5, Box::new(|_| {})).unwrap();
+		//? The game is paused, only the menus move on.
+		let paused = |layer| if layer == Layer::Ui { 0.6 } else { 0.0 };
+
+		let due: Vec<_> = scheduler.take_due(&paused).into_iter().map(|task| task.id).collect();
+		assert_eq!(due, vec![every]);
+		assert!(scheduler.take_due(&|_| 0.0).is_empty());
+
+		let running = |_| 1.0;
+		let due: Vec<_> = scheduler.take_due(&running).into_iter().map(|task| task.id).collect();
+		assert_eq!(due, vec![once]);
+	}
+
+	#[test]
+	fn scheduler_repeats_until_cancelled() {
+		let mut scheduler = Scheduler::new();
+		let every = scheduler.every(Layer::Ui, 0.5, Box::new(|_| {})).unwrap();
+
+		for _ in 0..3 {
+			let mut due = scheduler.take_due(&|_| 0.5);
+			assert_eq!(due.len(), 1);
+			scheduler.ran(due.pop().unwrap());
+		}
+
+		//? Cancelled by its own callback.
+		let mut due = scheduler.take_due(&|_| 0.5);
+		scheduler.cancel(every);
+		scheduler.ran(due.pop().unwrap());
+		assert!(scheduler.take_due(&|_| 10.0).is_empty());
+	}
+	#[test]
+	fn scheduler_drops_the_periods_missed_during_a_hitch() {
+		let mut scheduler = Scheduler::new();
+		assert!(scheduler.every(Layer::Ui, 0.0, Box::new(|_| {})).is_none());
+		assert!(scheduler.every(Layer::Ui, -1.0, Box::new(|_| {})).is_none());
+
+		scheduler.every(Layer::Ui, 0.5, Box::new(|_| {})).unwrap();
+		let mut due = scheduler.take_due(&|_| 3.0);
+		assert_eq!(due.len(), 1);
+		scheduler.ran(due.pop().unwrap());
+
+		//? Five periods were missed, but it only runs again a period later.
+		assert!(scheduler.take_due(&|_| 0.25).is_empty());
+		assert_eq!(scheduler.take_due(&|_| 0.25).len(), 1);
+	}
+}
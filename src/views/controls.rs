@@ -0,0 +1,144 @@
+use ::phi::{Phi, View, ViewAction};
+use ::phi::input::{self, Action, Binding, ACTIONS};
+use ::phi::text::{Align, CopyText, TextOptions};
+
+use ::views::shared::{Menu, MenuEvent, MenuItem, MenuKind};
+
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::pixels::Color;
+
+const FONT: &'static str = "assets/belligerent.ttf";
+
+/// Lists every action along with the keys bound to it, and lets the player
+/// bind a new key to the selected action. Bindings are saved when leaving.
+///
+/// It is pushed on top of the view which opened it, and returns to it.
+pub struct ControlsView {
+	menu: Menu<Action>,
+	/// Whether the next key pressed should be bound to the selected action.
+	waiting: bool,
+	/// Why the last key pressed could not be bound, shown instead of the
+	/// prompt until another one is pressed.
+	refused: Option<String>,
+}
+
+impl ControlsView {
+	pub fn new(phi: &mut Phi) -> ControlsView {
+		let mut menu = Menu::new(ACTIONS.iter()
+			.map(|&action| MenuItem::value(action, action.label(), ""))
+			.collect());
+		menu.theme.width = 560.0;
+		menu.theme.item_h = 40.0;
+		menu.theme.size = 26;
+		menu.theme.selected_size = 26;
+
+		let mut view = ControlsView {
+			menu: menu,
+			waiting: false,
+			refused: None,
+		};
+		view.show_bindings(phi);
+		view
+	}
+
+	/// Shows the inputs bound to every action, or a prompt next to the
+	/// action which is being bound.
+	fn show_bindings(&mut self, phi: &Phi) {
+		let selected = self.menu.selected();
+
+		for item in &mut self.menu.items {
+			let value =
+				if self.waiting && Some(item.id) == selected {
+					self.refused.clone().unwrap_or("Press a key or button...".to_string())
+				} else {
+					phi.input.bindings(item.id).iter()
+						.map(|binding| binding.name())
+						.collect::<Vec<_>>()
+						.join(", ")
+				};
+			item.kind = MenuKind::Value(value);
+		}
+	}
+
+	/// Binds the first key or button pressed during this frame, if any, to
+	/// the selected action.
+	fn bind_pressed(&mut self, phi: &mut Phi) {
+		//? Escape cancels, so that the player cannot get stuck with a
+		//? binding they did not want.
+		let player = phi.input.player;
+		let pressed = phi.events.now.keys.iter()
+			.find(|&&(_, pressed)| pressed)
+			.map(|&(key, _)| Binding::Key(key))
+			.or_else(|| phi.events.now.buttons.iter()
+				.find(|&&(p, _, pressed)| p == player && pressed)
+				.map(|&(_, button, _)| Binding::Button(button)));
+
+		match (pressed, self.menu.selected()) {
+			(Some(Binding::Key(Keycode::Escape)), _) => {
+				self.waiting = false;
+				self.refused = None;
+			},
+			(Some(binding), Some(action)) => {
+				if phi.input.can_bind(action, binding) {
+					phi.input.unbind_like(action, binding);
+					phi.input.bind(action, binding);
+					self.waiting = false;
+					self.refused = None;
+				} else {
+					self.refused = Some(format!("{} is needed by the menus", binding.name()));
+				}
+			},
+			_ => return,
+		}
+
+		//? Binding an input removes it from the actions used at the same time.
+		self.show_bindings(phi);
+	}
+}
+
+impl View for ControlsView {
+	fn render(&mut self, phi: &mut Phi, _: f64) -> ViewAction {
+		if phi.events.now.quit {
+			return ViewAction::Quit;
+		}
+
+		if self.waiting {
+			self.bind_pressed(phi);
+		} else {
+			match self.menu.update(phi) {
+				MenuEvent::Back => {
+					if let Err(e) = phi.input.save(input::BINDINGS_PATH) {
+						println!("Could not save the controls: {}", e);
+					}
+					return ViewAction::Pop;
+				},
+				MenuEvent::Activated(_) => {
+					self.waiting = true;
+					self.show_bindings(phi);
+				},
+				_ => {},
+			}
+		}
+
+		// Clear the screen.
+		phi.renderer.set_draw_color(Color::RGB(0,0,0));
+		phi.renderer.clear();
+
+		self.menu.render(phi);
+
+		let bounds = self.menu.bounds(phi);
+		let white = Color::RGB(255,255,255);
+		let options = TextOptions::aligned(Align::Center, bounds.w);
+
+		if let Some(title) = phi.layout_text("Controls", FONT, 34, &options) {
+			phi.renderer.copy_text(&title, bounds.x, bounds.y - title.h - 10.0, white);
+		}
+
+		let hint = "Confirm to rebind, Back to save and leave";
+		if let Some(hint) = phi.layout_text(hint, FONT, 18, &options) {
+			phi.renderer.copy_text(&hint, bounds.x, bounds.y + bounds.h + 10.0, Color::RGB(220,220,220));
+		}
+
+		ViewAction::None
+	}
+}
@@ -1,19 +1,43 @@
 use ::phi::{Phi, View, ViewAction};
+use ::phi::clock::Layer;
+use ::phi::config::DIFFICULTIES;
 use ::phi::data::Rectangle;
 use ::phi::gfx::{CopySprite, Sprite};
+use ::phi::input::Action;
+use ::phi::text::{Align, CopyText, TextOptions};
 
-use ::views::shared::Background;
+use ::views::shared::{Background, Menu, MenuEvent, MenuItem};
 
+use ::sdl2::controller::Axis;
 use ::sdl2::pixels::Color;
+use ::sdl2::render::BlendMode;
 
 // Constants
-/// Pixels traveled by the player's ship every second, when moving
+/// Pixels traveled by the player's ship every second, when moving. It can
+/// be tuned with the debug overlay.
 const PLAYER_SPEED: f64 = 180.0;
 /// Ship Size
 const SHIP_W: f64 = 43.0;
 const SHIP_H: f64 = 39.0;
 
-const DEBUG: bool = false;
+const FONT: &'static str = "assets/belligerent.ttf";
+
+/// What can be chosen in the pause menu.
+#[derive(Clone, Copy, PartialEq)]
+enum PauseChoice {
+	Resume,
+	Restart,
+	Options,
+	Quit,
+}
+
+/// The choices of the pause menu, in the order in which they are listed.
+const PAUSE_CHOICES: [(&'static str, PauseChoice); 4] = [
+	("Resume", PauseChoice::Resume),
+	("Restart", PauseChoice::Restart),
+	("Options", PauseChoice::Options),
+	("Quit to Menu", PauseChoice::Quit),
+];
 
 /// The different states our ship might be in. In the image, they're
 /// ordered from left to right, them from top to bottom.
@@ -39,6 +63,15 @@ struct Ship {
 // View definition
 pub struct ShipView {
   player: Ship,
+  /// Whether the game is driven by a replay, as an attract-mode demo.
+  demo: bool,
+  /// Whether this game is being recorded as the demo, until it is left.
+  recording_demo: bool,
+  /// Whether the pause menu is shown, in which case the clock is paused.
+  paused: bool,
+  pause_menu: Menu<PauseChoice>,
+  /// The speed of the ship, which can be tuned with the debug overlay.
+  speed: f64,
 
 	bg_back: Background,
 	bg_middle: Background,
@@ -67,6 +100,19 @@ impl ShipView {
 			}
 		}
 
+    phi.console.register_queued("speed", "speed <pixels per second>: changes the speed of the ship");
+
+    let recording_demo = match phi.demo_recording.take() {
+      Some(path) => match phi.start_recording(&path) {
+        Ok(()) => true,
+        Err(e) => {
+          println!("Could not record the demo to {}: {}", path, e);
+          false
+        },
+      },
+      None => false,
+    };
+
     ShipView {
       player: Ship {
         rect: Rectangle {
@@ -78,6 +124,13 @@ impl ShipView {
 				sprites: sprites,
 				current: ShipFrame::MidNorm,
       },
+      demo: false,
+      recording_demo: recording_demo,
+      paused: false,
+      pause_menu: Menu::new(PAUSE_CHOICES.iter()
+        .map(|&(label, choice)| MenuItem::label(choice, label))
+        .collect()),
+      speed: PLAYER_SPEED,
 
 			bg_back: Background {
 				pos: 0.0,
@@ -98,33 +151,67 @@ impl ShipView {
   }
 }
 
-impl View for ShipView {
-  fn render(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
-    if phi.events.now.quit || phi.events.now.key_escape == Some(true) {
-      return ViewAction::ChangeView(
-      	Box::new(::views::main_menu::MainMenuView::new(phi))
-      );
-    }
+impl ShipView {
+  /// A game played by the replay which `phi` is playing back. It returns to
+  /// the main menu when the replay ends or when the player presses anything.
+  pub fn demo(phi: &mut Phi) -> ShipView {
+    // Only a game the player starts is recorded as the demo, not the demo.
+    let demo_recording = phi.demo_recording.take();
+    let view = ShipView {
+      demo: true,
+      .. ShipView::new(phi)
+    };
+    phi.demo_recording = demo_recording;
+    view
+  }
+}
 
+impl ShipView {
+  /// Moves the ship according to the player's input.
+  fn update(&mut self, phi: &mut Phi, elapsed: f64) {
     // Move the player's ship
-    let diagonal =
-      (phi.events.key_up ^ phi.events.key_down) &&
-      (phi.events.key_left ^ phi.events.key_right);
+    let (up, down, left, right) = (
+      phi.input.held(Action::MoveUp),
+      phi.input.held(Action::MoveDown),
+      phi.input.held(Action::MoveLeft),
+      phi.input.held(Action::MoveRight),
+    );
+    let diagonal = (up ^ down) && (left ^ right);
 
     let moved =
       if diagonal { 1.0 / 2.0f64.sqrt() }
-      else { 1.0 } * PLAYER_SPEED * elapsed;
+      else { 1.0 } * self.speed * elapsed;
 
-    let dx = match (phi.events.key_left, phi.events.key_right) {
-      (true,true) | (false,false) => 0.0,
-      (true,false) => -moved,
-      (false,true) => moved,
-    };
-    let dy = match (phi.events.key_up, phi.events.key_down) {
-      (true,true) | (false,false) => 0.0,
-      (true,false) => -moved,
-      (false,true) => moved,
-    };
+    let (digital_dx, digital_dy) = (
+      match (left, right) {
+        (true,true) | (false,false) => 0.0,
+        (true,false) => -moved,
+        (false,true) => moved,
+      },
+      match (up, down) {
+        (true,true) | (false,false) => 0.0,
+        (true,false) => -moved,
+        (false,true) => moved,
+      },
+    );
+
+    // When the stick is pushed, the ship moves proportionally to how far it
+    // is pushed, rather than at full speed like with the keyboard.
+    let player = phi.input.player;
+    let (stick_x, stick_y) = (
+      phi.events.gamepads.axis(player, Axis::LeftX),
+      phi.events.gamepads.axis(player, Axis::LeftY),
+    );
+    let stick_len = (stick_x * stick_x + stick_y * stick_y).sqrt();
+
+    let (dx, dy) =
+      if stick_len > 0.0 {
+        // Corners of the stick's square range should not be faster than
+        // its edges.
+        let scale = self.speed * elapsed / stick_len.max(1.0);
+        (stick_x * scale, stick_y * scale)
+      }
+      else { (digital_dx, digital_dy) };
 
     self.player.rect.x += dx;
     self.player.rect.y += dy;
@@ -156,8 +243,20 @@ impl View for ShipView {
 			else if dx < 0.0 && dy > 0.0   { ShipFrame::DownSlow }
 			else { unreachable!() };
 
-    // View logic 'ere
+    // Show the bounding box of the ship and where it is heading (for
+    // debugging purposes); the arrow spans a quarter second of movement.
+    let rect = self.player.rect;
+    let center = (rect.x + rect.w / 2.0, rect.y + rect.h / 2.0);
+    phi.debug_draw.rect(rect, Color::RGB(200,200,50));
+    if elapsed > 0.0 {
+      let seconds = 0.25 / elapsed;
+      phi.debug_draw.arrow(center, (center.0 + dx * seconds, center.1 + dy * seconds), Color::RGB(50,200,200));
+    }
+  }
 
+  /// Draws the game as it currently is, scrolling the backgrounds by
+  /// `elapsed`, which is 0 while paused.
+  fn draw(&mut self, phi: &mut Phi, elapsed: f64) {
     phi.renderer.set_draw_color(Color::RGB(0,0,0));
     phi.renderer.clear();
 
@@ -166,12 +265,6 @@ impl View for ShipView {
 		self.bg_middle.render(&mut phi.renderer, elapsed);
 		self.bg_front.render(&mut phi.renderer, elapsed);
 		
-		// Render the bounding box (for debugging purposes)
-		if DEBUG {
-    	// View Rendering here
-    	phi.renderer.set_draw_color(Color::RGB(200,200,50));
-    	phi.renderer.fill_rect(self.player.rect.to_sdl().unwrap());
-		}
 		// Render the ship
 		phi.renderer.copy_sprite(
 			&self.player.sprites[self.player.current as usize],
@@ -194,9 +287,158 @@ impl View for ShipView {
 			// takes care of the rest.
 		// 	self.player.rect.to_sdl()
 		// );
+  }
+
+  /// Shows the variables of the game in the debug overlay, where they can
+  /// be changed.
+  fn tweak(&mut self, phi: &mut Phi) {
+    let debug = &mut phi.debug;
+    debug.float("Ship speed", &mut self.speed, 0.0, 600.0);
+    debug.float("Back layer speed", &mut self.bg_back.vel, 0.0, 200.0);
+    debug.float("Middle layer speed", &mut self.bg_middle.vel, 0.0, 200.0);
+    debug.float("Front layer speed", &mut self.bg_front.vel, 0.0, 200.0);
+    debug.float("Time scale", &mut phi.clock.scale, 0.0, 2.0);
+    debug.checkbox("Show shapes", &mut phi.debug_draw.enabled);
+
+    let current = phi.config.difficulty;
+    let mut difficulty = DIFFICULTIES.iter()
+      .position(|d| *d == current)
+      .unwrap_or(0);
+    let labels: Vec<&str> = DIFFICULTIES.iter().map(|d| d.label()).collect();
+    debug.choice("Difficulty", &mut difficulty, &labels);
+    phi.config.difficulty = DIFFICULTIES[difficulty];
+
+    debug.value("Ship", format!("{:.0}, {:.0}", self.player.rect.x, self.player.rect.y));
+  }
+
+  /// Returns to the main menu, taking the commands of the game out of the
+  /// console.
+  fn leave(&mut self, phi: &mut Phi) -> ViewAction {
+    phi.console.unregister("speed");
+    if self.recording_demo {
+      phi.stop_recording();
+    }
+    ViewAction::ChangeView(Box::new(::views::main_menu::MainMenuView::new(phi)))
+  }
+
+  /// Runs the console commands handled by the game.
+  fn run_commands(&mut self, phi: &mut Phi) {
+    for invocation in phi.console.invocations.clone() {
+      if invocation.name == "speed" {
+        match invocation.args.first().and_then(|arg| arg.parse().ok()) {
+          Some(speed) => self.speed = speed,
+          None => phi.console.print("Usage: speed <pixels per second>"),
+        }
+      }
+    }
+  }
+
+  fn pause(&mut self, phi: &mut Phi) {
+    if !self.paused {
+      self.paused = true;
+      phi.clock.paused = true;
+      self.pause_menu.select(PauseChoice::Resume);
+    }
+  }
+
+  /// Handles the input of the pause menu, and returns the action to take if
+  /// the game is left.
+  fn pause_input(&mut self, phi: &mut Phi) -> Option<ViewAction> {
+    match self.pause_menu.update(phi) {
+      MenuEvent::Back | MenuEvent::Activated(PauseChoice::Resume) => {
+        self.paused = false;
+        phi.clock.paused = false;
+      },
+      MenuEvent::Activated(PauseChoice::Restart) => {
+        //? The demo ends here, since the new game does not start where the
+        //? recorded one did.
+        if self.recording_demo {
+          phi.stop_recording();
+        }
+        return Some(ViewAction::ChangeView(Box::new(ShipView::new(phi))));
+      },
+      //? The options are shown on top of the game, which is kept paused
+      //? until they are closed.
+      MenuEvent::Activated(PauseChoice::Options) =>
+        return Some(ViewAction::Push(Box::new(::views::options::OptionsView::new(phi)))),
+      MenuEvent::Activated(PauseChoice::Quit) =>
+        return Some(self.leave(phi)),
+      _ => {},
+    }
+
+    None
+  }
+
+  /// Dims the frozen game and draws the pause menu over it.
+  fn draw_pause(&mut self, phi: &mut Phi) {
+    let (win_w, win_h) = phi.output_size();
+
+    phi.renderer.set_blend_mode(BlendMode::Blend);
+    phi.renderer.set_draw_color(Color::RGBA(0,0,0,160));
+    phi.renderer.fill_rect(Rectangle {
+      x: 0.0,
+      y: 0.0,
+      w: win_w as f64,
+      h: win_h as f64,
+    }.to_sdl().unwrap());
+    phi.renderer.set_blend_mode(BlendMode::None);
+
+    self.pause_menu.render(phi);
+
+    let top = self.pause_menu.bounds(phi).y;
+    let title_options = TextOptions::aligned(Align::Center, win_w as f64);
+    if let Some(title) = phi.layout_text("Paused", FONT, 48, &title_options) {
+      phi.renderer.copy_text(&title, 0.0, top - title.h - 20.0, Color::RGB(255,255,255));
+    }
+  }
+}
+
+impl View for ShipView {
+  fn render(&mut self, phi: &mut Phi, _: f64) -> ViewAction {
+    if self.demo && (phi.events.now.live_input || !phi.is_replaying()) {
+      phi.stop_replay();
+      return self.leave(phi);
+    }
+
+    if phi.events.now.quit {
+      return self.leave(phi);
+    }
+
+    //? The press of Back which opens the pause menu must not also be seen
+    //? by the menu, which would close it right away.
+    if self.paused {
+      if let Some(action) = self.pause_input(phi) {
+        return action;
+      }
+    } else if phi.input.pressed(Action::Back) {
+      self.pause(phi);
+    } else {
+      phi.profiler.begin("update");
+      let elapsed = phi.clock.delta(Layer::Gameplay);
+      self.update(phi, elapsed);
+      phi.profiler.end();
+    }
+
+    self.tweak(phi);
+    self.run_commands(phi);
+
+    phi.profiler.begin("draw");
+    let elapsed = phi.clock.delta(Layer::Background);
+    self.draw(phi, elapsed);
+    phi.profiler.end();
+    if self.paused {
+      self.draw_pause(phi);
+    }
 
     ViewAction::None
   }
+
+  fn focus_lost(&mut self, phi: &mut Phi) {
+    // The demo is left as soon as the player comes back anyway.
+    if !self.demo {
+      self.pause(phi);
+    }
+  }
 }
 // pub struct DefaultView;
 // 
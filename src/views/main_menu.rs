@@ -1,16 +1,37 @@
 use ::phi::{Phi, View, ViewAction};
-use ::phi::data::Rectangle;
-use ::phi::gfx::{Sprite, CopySprite};
+use ::phi::clock::Layer;
+use ::phi::gfx::Sprite;
+use ::phi::markup::{CopyRichText, RichLayout};
+use ::phi::text::Align;
+use ::phi::tween::{Animation, Curve, Ease, Parallel, Repeat, Sequence, Tween, Tweened};
 
-use ::views::shared::Background;
+use ::views::shared::{Background, Menu, MenuEvent, MenuItem};
 
 use ::sdl2::pixels::Color;
 
+/// The replay played when the menu is left alone for `DEMO_DELAY` seconds.
+const DEMO_REPLAY: &'static str = "assets/demo.replay";
+const DEMO_DELAY: f64 = 20.0;
+
+/// How long the box takes to swing from one side of its wobble to the other.
+const WOBBLE_SWING: f64 = ::std::f64::consts::PI / 4.0;
+
 pub struct MainMenuView {
 	actions: Vec<Action>,
-	selected: i8, //? Use an i8 (0..) so we don't decrement below 0
+	/// Lists the labels of `actions`, identified by their index.
+	menu: Menu<usize>,
+	/// The time which passed for the UI, which animates the title.
 	elapsed: f64,
-	
+	/// Makes the box wobble slightly, by changing its margin and width.
+	wobble: Box<Animation>,
+	margin: Tweened<f64>,
+	width: Tweened<f64>,
+	title: RichLayout,
+	/// For how long, in seconds, the player has not touched anything.
+	idle: f64,
+	/// Whether the demo could not be loaded, so that it is not tried again.
+	demo_missing: bool,
+
 	bg_back: Background,
 	bg_middle: Background,
 	bg_front: Background,
@@ -18,19 +39,41 @@ pub struct MainMenuView {
 
 impl MainMenuView {
 	pub fn new(phi: &mut Phi) -> MainMenuView {
+		let actions = vec![
+			Action::new("New Game", Box::new(|phi| {
+				ViewAction::ChangeView(Box::new(::views::game::ShipView::new(phi)))
+			})),
+			Action::new("Options", Box::new(|phi| {
+				ViewAction::Push(Box::new(::views::options::OptionsView::new(phi)))
+			})),
+			Action::new("Quit", Box::new(|_| {
+				ViewAction::Quit
+			})),
+		];
+		let menu = Menu::new(actions.iter().enumerate()
+			.map(|(i, action)| MenuItem::label(i, action.label))
+			.collect());
+
+		let margin = Tweened::new(menu.theme.margin - 5.0);
+		let width = Tweened::new(menu.theme.width);
+		let wobble: Vec<Box<Animation>> = vec![
+			swing(&margin, menu.theme.margin - 5.0, menu.theme.margin + 5.0),
+			swing(&width, menu.theme.width - 5.0, menu.theme.width + 5.0),
+		];
+
 		MainMenuView {
-			actions: vec![
-				Action::new(phi, "New Game", Box::new(|phi| {
-					ViewAction::ChangeView(Box::new(::views::game::ShipView::new(phi)))
-				})),
-				Action::new(phi, "Quit", Box::new(|_| {
-					ViewAction::Quit
-				})),
-			],
-			//? Start with nothing selected.
-			selected: 0,
+			actions: actions,
+			menu: menu,
 			elapsed: 0.0,
-			
+			wobble: Box::new(Parallel::new(wobble)),
+			margin: margin,
+			width: width,
+			title: phi.layout_markup(
+				"[color=#ffcc33][wave]ArcadeRS[/wave][/color] [b]Shooter[/b]",
+				"assets/belligerent.ttf", 48, Align::Center),
+			idle: 0.0,
+			demo_missing: false,
+
 			bg_back: Background {
 				pos: 0.0,
 				vel: 20.0,
@@ -52,120 +95,95 @@ impl MainMenuView {
 
 impl View for MainMenuView {
 	fn render(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
-		if phi.events.now.quit || phi.events.now.key_escape == Some(true) {
+		if phi.events.now.quit {
 			return ViewAction::Quit;
 		}
 
-		// Execute the currently selected option
-		if phi.events.now.key_space == Some(true) || phi.events.now.key_return == Some(true) {
+		// Show a demo when the player has not done anything for a while.
+		let touched = {
+			let now = &phi.events.now;
+			!now.keys.is_empty() || !now.buttons.is_empty() ||
+				now.mouse.moved || !now.touches_started.is_empty()
+		};
+		self.idle = if touched { 0.0 } else { self.idle + elapsed };
+
+		if self.idle > DEMO_DELAY && !self.demo_missing {
+			if phi.play_replay(DEMO_REPLAY) {
+				return ViewAction::ChangeView(Box::new(::views::game::ShipView::demo(phi)));
+			}
+			//? Do not read the disk again every few seconds when it is missing.
+			println!("Could not load the demo from {}", DEMO_REPLAY);
+			self.demo_missing = true;
+		}
+
+		match self.menu.update(phi) {
 			//? Using the (self.attr_which_is_a_closure)(phi) syntax so that rust
 			//? doesn't confuse it with an invocation of a function named `func`.
 			//?
 			//? Necessary because Rust allows a method to share the same name as an
 			//? attribute of a struct. Alledgedly useful for defining accessors.
-			return (self.actions[self.selected as usize].func)(phi);
-		}
-		
-		// Change the selected action based on input
-		if phi.events.now.key_up == Some(true) {
-			self.selected -= 1;
-			//? Handle looping on the list of actions
-			if self.selected < 0 {
-				self.selected = self.actions.len() as i8 - 1;
-			}
+			MenuEvent::Activated(i) => return (self.actions[i].func)(phi),
+			MenuEvent::Back => return ViewAction::Quit,
+			_ => {},
 		}
-		if phi.events.now.key_down == Some(true) {
-			self.selected += 1;
-			//? Handle looping on the list of actions
-			if self.selected >= self.actions.len() as i8 {
-				self.selected = 0;
-			}
-		}
-		
+
 		// Clear the screen.
 		phi.renderer.set_draw_color(Color::RGB(0,0,0));
 		phi.renderer.clear();
 
 		// Render the backgrounds
-		self.bg_back.render(&mut phi.renderer, elapsed);
-		self.bg_middle.render(&mut phi.renderer, elapsed);
-		self.bg_front.render(&mut phi.renderer, elapsed);
-		
-		let (win_w,win_h) = phi.output_size();
-		let label_h = 50.0;
-		let border_width = 3.0;
-		
-		self.elapsed += elapsed * 4.0;
-		let margin_h = 10.0 + 5.0 * (self.elapsed + 1.0).sin();	
-		let box_w = 360.0 + 5.0 * self.elapsed.sin();
-		
-		let box_h = self.actions.len() as f64 * label_h;
-		
-		
-		// Render the border of the coloured box
-		phi.renderer.set_draw_color(Color::RGB(70,15,70));
-		phi.renderer.fill_rect(Rectangle {
-			w: box_w + border_width * 2.0,
-			h: box_h + border_width * 2.0 + margin_h * 2.0,
-			x: (win_w as f64 - box_w) / 2.0 - border_width,
-			y: (win_h as f64 - box_h) / 2.0 - margin_h - border_width,
-		}.to_sdl().unwrap());
-		
-		// Render the coloured box which holds the labels
-		phi.renderer.set_draw_color(Color::RGB(140,30,140));
-		phi.renderer.fill_rect(Rectangle {
-			w: box_w,
-			h: box_h + margin_h * 2.0,
-			x: (win_w as f64 - box_w) / 2.0,
-			y: (win_h as f64 - box_h) / 2.0 - margin_h,
-		}.to_sdl().unwrap());
-		
-		for (i, action) in self.actions.iter().enumerate() {
-			if self.selected as usize == i {
-				let (w,h) = action.hover_sprite.size();
-				phi.renderer.copy_sprite(&action.hover_sprite, Rectangle {
-					x: (win_w as f64 - w) / 2.0,
-					//? Place Every element under the previous one
-					y: (win_h as f64 - box_h + label_h - h) / 2.0 + label_h * i as f64,
-					w: w,
-					h: h,
-				});			
-			} else {
-				let (w,h) = action.idle_sprite.size();
-				phi.renderer.copy_sprite(&action.idle_sprite, Rectangle {
-					x: (win_w as f64 - w) / 2.0,
-					//? Place Every element under the previous one
-					y: (win_h as f64 - box_h + label_h - h) / 2.0 + label_h * i as f64,
-					w: w,
-					h: h,
-				});				
-			}
+		let scenery = phi.clock.delta(Layer::Background);
+		self.bg_back.render(&mut phi.renderer, scenery);
+		self.bg_middle.render(&mut phi.renderer, scenery);
+		self.bg_front.render(&mut phi.renderer, scenery);
 
-		}
+		// The box wobbles slightly.
+		let ui = phi.clock.delta(Layer::Ui);
+		self.elapsed += ui;
+		self.wobble.advance(ui);
+		self.menu.theme.margin = self.margin.get();
+		self.menu.theme.width = self.width.get();
+
+		self.menu.render(phi);
+
+		// Render the title above the box
+		let (win_w, _) = phi.output_size();
+		let top = self.menu.bounds(phi).y;
+		phi.renderer.copy_rich_text(&self.title,
+			(win_w as f64 - self.title.w) / 2.0,
+			top - self.title.h - 20.0,
+			Color::RGB(255,255,255),
+			self.elapsed);
 
 		ViewAction::None
 	}
 }
 
+/// Swings `value` back and forth between `low` and `high`, forever.
+fn swing(value: &Tweened<f64>, low: f64, high: f64) -> Box<Animation> {
+	let ease = Ease::InOut(Curve::Sine);
+	let steps: Vec<Box<Animation>> = vec![
+		Box::new(Tween::to(value, high, WOBBLE_SWING).ease(ease)),
+		Box::new(Tween::to(value, low, WOBBLE_SWING).ease(ease)),
+	];
+	Box::new(Repeat::forever(Box::new(Sequence::new(steps))))
+}
+
 struct Action {
 	/// The function which should be executed if the action is chosen.
 	//? Stored in a `Box` because `Fn` is a trait, so we can only interact
 	//? with it via a pointer.
 	func: Box<Fn(&mut Phi) -> ViewAction>,
 
-	/// The sprite which is rendered when not the focus
-	idle_sprite: Sprite,
-
-	/// The sprite for when in focus
-	hover_sprite: Sprite,
+	/// The text displayed for this action in the menu.
+	label: &'static str,
 }
 
 impl Action {
-	fn new(phi: &mut Phi, label: &'static str, func: Box<Fn(&mut Phi) -> ViewAction>) -> Action {
+	fn new(label: &'static str, func: Box<Fn(&mut Phi) -> ViewAction>) -> Action {
 		Action {
 			func: func,
-			idle_sprite: phi.ttf_str_sprite(label, "assets/belligerent.ttf", 32, Color::RGB(220,220,220)).unwrap(),
-			hover_sprite: phi.ttf_str_sprite(label, "assets/belligerent.ttf", 38, Color::RGB(255,255,255)).unwrap(),
+			label: label,
 		}
 	}
-}
\ No newline at end of file
+}
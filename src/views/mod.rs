@@ -1,3 +1,5 @@
+pub mod controls;
 pub mod game;
 pub mod main_menu;
+pub mod options;
 pub mod shared;
\ No newline at end of file
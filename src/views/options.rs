@@ -0,0 +1,121 @@
+use ::phi::{Phi, View, ViewAction};
+use ::phi::config::{self, DIFFICULTIES, RESOLUTIONS, VOLUME_STEP};
+use ::phi::text::{Align, CopyText, TextOptions};
+
+use ::views::shared::{Menu, MenuEvent, MenuItem};
+
+use ::sdl2::pixels::Color;
+
+const FONT: &'static str = "assets/belligerent.ttf";
+
+/// A setting listed by the options view.
+#[derive(Clone, Copy, PartialEq)]
+enum Row {
+	MusicVolume,
+	EffectsVolume,
+	Fullscreen,
+	Resolution,
+	VSync,
+	Difficulty,
+	Controls,
+}
+
+/// Lets the player change the settings of `Phi::config`, which are applied
+/// as soon as they change and saved when leaving. Like `ControlsView`, it is
+/// pushed on top of the view which opened it.
+pub struct OptionsView {
+	menu: Menu<Row>,
+}
+
+impl OptionsView {
+	pub fn new(phi: &mut Phi) -> OptionsView {
+		let config = &phi.config;
+
+		let resolutions = RESOLUTIONS.iter().map(|&(w, h)| format!("{}x{}", w, h)).collect();
+		let resolution = RESOLUTIONS.iter().position(|r| *r == config.resolution).unwrap_or(0);
+		let difficulties = DIFFICULTIES.iter().map(|d| d.label().to_string()).collect();
+		let difficulty = DIFFICULTIES.iter().position(|d| *d == config.difficulty).unwrap_or(0);
+
+		let mut menu = Menu::new(vec![
+			MenuItem::slider(Row::MusicVolume, "Music Volume", config.music_volume, VOLUME_STEP),
+			MenuItem::slider(Row::EffectsVolume, "Effects Volume", config.effects_volume, VOLUME_STEP),
+			MenuItem::toggle(Row::Fullscreen, "Fullscreen", config.fullscreen),
+			MenuItem::choice(Row::Resolution, "Resolution", resolutions, resolution),
+			MenuItem::toggle(Row::VSync, "VSync", config.vsync),
+			MenuItem::choice(Row::Difficulty, "Difficulty", difficulties, difficulty),
+			MenuItem::submenu(Row::Controls, "Controls"),
+		]);
+		menu.theme.width = 560.0;
+		menu.theme.item_h = 40.0;
+		menu.theme.size = 26;
+		menu.theme.selected_size = 26;
+
+		OptionsView {
+			menu: menu,
+		}
+	}
+
+	/// Copies the value of `row` from the menu to the settings, and applies
+	/// them.
+	fn apply(&self, phi: &mut Phi, row: Row) {
+		let menu = &self.menu;
+
+		match row {
+			Row::MusicVolume => phi.config.music_volume = menu.slider(row),
+			Row::EffectsVolume => phi.config.effects_volume = menu.slider(row),
+			Row::Fullscreen => phi.config.fullscreen = menu.toggle(row),
+			Row::Resolution => phi.config.resolution = RESOLUTIONS[menu.choice(row)],
+			Row::VSync => phi.config.vsync = menu.toggle(row),
+			Row::Difficulty => phi.config.difficulty = DIFFICULTIES[menu.choice(row)],
+			Row::Controls => {},
+		}
+
+		if row == Row::Fullscreen || row == Row::Resolution {
+			phi.apply_config();
+		}
+	}
+}
+
+impl View for OptionsView {
+	fn render(&mut self, phi: &mut Phi, _: f64) -> ViewAction {
+		if phi.events.now.quit {
+			return ViewAction::Quit;
+		}
+
+		match self.menu.update(phi) {
+			MenuEvent::Back => {
+				if let Err(e) = phi.config.save(config::CONFIG_PATH) {
+					println!("Could not save the settings: {}", e);
+				}
+				return ViewAction::Pop;
+			},
+			MenuEvent::Activated(Row::Controls) =>
+				return ViewAction::Push(Box::new(::views::controls::ControlsView::new(phi))),
+			MenuEvent::Changed(row) => self.apply(phi, row),
+			_ => {},
+		}
+
+		// Clear the screen.
+		phi.renderer.set_draw_color(Color::RGB(0,0,0));
+		phi.renderer.clear();
+
+		self.menu.render(phi);
+
+		let bounds = self.menu.bounds(phi);
+		let white = Color::RGB(255,255,255);
+		let options = TextOptions::aligned(Align::Center, bounds.w);
+
+		if let Some(title) = phi.layout_text("Options", FONT, 34, &options) {
+			phi.renderer.copy_text(&title, bounds.x, bounds.y - title.h - 10.0, white);
+		}
+
+		let hint =
+			if self.menu.selected() == Some(Row::VSync) { "VSync changes when the game restarts" }
+			else { "Left and Right to change, Back to save and leave" };
+		if let Some(hint) = phi.layout_text(hint, FONT, 18, &options) {
+			phi.renderer.copy_text(&hint, bounds.x, bounds.y + bounds.h + 10.0, Color::RGB(220,220,220));
+		}
+
+		ViewAction::None
+	}
+}
@@ -1,5 +1,10 @@
+use ::phi::{Events, Phi};
+use ::phi::events::EditKey;
 use ::phi::gfx::{Sprite,CopySprite};
 use ::phi::data::Rectangle;
+use ::phi::input::Action;
+use ::phi::text::{Align, CopyText, TextLayout, TextOptions};
+use ::sdl2::pixels::Color;
 use ::sdl2::render::Renderer;
 
 #[derive(Clone)]
@@ -41,3 +46,686 @@ impl Background {
 		}
 	}
 }
+
+
+/// A single-line text field, for example to enter a name for the high
+/// scores. Text input must be started on `phi.events` while the field has
+/// the focus, so that typed text is reported.
+pub struct TextField {
+	pub text: String,
+	/// Position of the caret, in characters.
+	cursor: usize,
+	/// Maximum number of characters.
+	pub max_len: usize,
+	blink: f64,
+}
+
+impl TextField {
+	pub fn new(max_len: usize) -> TextField {
+		TextField {
+			text: String::new(),
+			cursor: 0,
+			max_len: max_len,
+			blink: 0.0,
+		}
+	}
+
+	/// Replaces the content of the field and moves the caret to its end.
+	pub fn set_text(&mut self, text: &str) {
+		self.text = text.chars().take(self.max_len).collect();
+		self.cursor = self.text.chars().count();
+	}
+
+	/// The byte offset of the `index`-th character.
+	fn byte_offset(&self, index: usize) -> usize {
+		self.text.char_indices().nth(index).map(|(i, _)| i).unwrap_or(self.text.len())
+	}
+
+	/// Applies the text typed and the editing keys pressed during this
+	/// frame. Returns `true` if the player submitted the field.
+	pub fn update(&mut self, events: &Events, elapsed: f64) -> bool {
+		self.blink += elapsed;
+
+		for ch in events.now.text.chars() {
+			if self.text.chars().count() >= self.max_len {
+				break;
+			}
+			let at = self.byte_offset(self.cursor);
+			self.text.insert(at, ch);
+			self.cursor += 1;
+			self.blink = 0.0;
+		}
+
+		let mut submitted = false;
+		for key in &events.now.edit_keys {
+			let len = self.text.chars().count();
+
+			match *key {
+				EditKey::Backspace if self.cursor > 0 => {
+					self.cursor -= 1;
+					let at = self.byte_offset(self.cursor);
+					self.text.remove(at);
+				},
+				EditKey::Delete if self.cursor < len => {
+					let at = self.byte_offset(self.cursor);
+					self.text.remove(at);
+				},
+				EditKey::Left if self.cursor > 0 => self.cursor -= 1,
+				EditKey::Right if self.cursor < len => self.cursor += 1,
+				EditKey::Home => self.cursor = 0,
+				EditKey::End => self.cursor = len,
+				EditKey::Submit => submitted = true,
+				_ => {},
+			}
+			self.blink = 0.0;
+		}
+
+		submitted
+	}
+
+	/// Draws the field inside of `rect`, with the text composed by the input
+	/// method, if any, underlined after the caret.
+	pub fn render(&self, phi: &mut Phi, rect: Rectangle, font_path: &'static str, size: i32) {
+		phi.renderer.set_draw_color(Color::RGB(20,5,20));
+		phi.renderer.fill_rect(rect.to_sdl().unwrap());
+		phi.renderer.set_draw_color(Color::RGB(140,30,140));
+		phi.renderer.draw_rect(rect.to_sdl().unwrap());
+
+		let padding = 6.0;
+		let white = Color::RGB(255,255,255);
+		let options = TextOptions::default();
+
+		let (before, after) = self.text.split_at(self.byte_offset(self.cursor));
+		let composition = phi.events.composition.as_ref().map(|c| c.0.clone()).unwrap_or(String::new());
+
+		let mut x = rect.x + padding;
+		let y = rect.y + padding;
+
+		if let Some(layout) = phi.layout_text(before, font_path, size, &options) {
+			phi.renderer.copy_text(&layout, x, y, white);
+			x += layout.w;
+		}
+
+		let caret_x = x;
+
+		if let Some(layout) = phi.layout_text(&composition, font_path, size, &options) {
+			phi.renderer.copy_text(&layout, x, y, Color::RGB(200,200,120));
+			if !composition.is_empty() {
+				phi.renderer.set_draw_color(Color::RGB(200,200,120));
+				phi.renderer.fill_rect(Rectangle {
+					x: x,
+					y: y + layout.h,
+					w: layout.w,
+					h: 1.0,
+				}.to_sdl().unwrap());
+			}
+			x += layout.w;
+		}
+
+		if let Some(layout) = phi.layout_text(after, font_path, size, &options) {
+			phi.renderer.copy_text(&layout, x, y, white);
+		}
+
+		//? The caret blinks, but stays visible while the player is typing.
+		if self.blink % 1.0 < 0.5 {
+			phi.renderer.set_draw_color(white);
+			phi.renderer.fill_rect(Rectangle {
+				x: caret_x,
+				y: y,
+				w: 2.0,
+				h: rect.h - padding * 2.0,
+			}.to_sdl().unwrap());
+		}
+	}
+}
+
+
+/// The colors and sizes of a `Menu`. The default is the purple box of the
+/// main menu.
+#[derive(Clone, Copy, Debug)]
+pub struct MenuTheme {
+	pub font: &'static str,
+	pub size: i32,
+	/// The size of the selected item's text, which grows when selected.
+	pub selected_size: i32,
+	pub border: Color,
+	pub background: Color,
+	pub text: Color,
+	pub selected_text: Color,
+	pub disabled_text: Color,
+	/// The color of the filled part of sliders.
+	pub slider: Color,
+	/// How the items which show no value are aligned in the box.
+	pub align: Align,
+	pub width: f64,
+	pub item_h: f64,
+	/// The space above the first item and below the last one.
+	pub margin: f64,
+	pub border_width: f64,
+	/// The space left of the labels and right of the values, for the items
+	/// which show a value.
+	pub padding: f64,
+}
+
+impl Default for MenuTheme {
+	fn default() -> MenuTheme {
+		MenuTheme {
+			font: "assets/belligerent.ttf",
+			size: 32,
+			selected_size: 38,
+			border: Color::RGB(70,15,70),
+			background: Color::RGB(140,30,140),
+			text: Color::RGB(220,220,220),
+			selected_text: Color::RGB(255,255,255),
+			disabled_text: Color::RGB(110,60,110),
+			slider: Color::RGB(255,204,51),
+			align: Align::Center,
+			width: 360.0,
+			item_h: 50.0,
+			margin: 10.0,
+			border_width: 3.0,
+			padding: 20.0,
+		}
+	}
+}
+
+/// What an item of a `Menu` holds, besides its label.
+#[derive(Clone, Debug)]
+pub enum MenuKind {
+	/// An item which is activated when chosen.
+	Label,
+	/// An item which opens another menu; activated like a label.
+	Submenu,
+	Toggle(bool),
+	/// A value between 0 and 1, changed by `step`.
+	Slider { value: f64, step: f64 },
+	/// One of `options`, which are cycled through.
+	Choice { options: Vec<String>, selected: usize },
+	/// A text shown on the right, such as the keys bound to an action; it
+	/// is activated like a label.
+	Value(String),
+}
+
+/// An item of a `Menu`, identified by `id` in the events it reports.
+#[derive(Clone, Debug)]
+pub struct MenuItem<T> {
+	pub id: T,
+	pub label: String,
+	pub kind: MenuKind,
+	/// Disabled items are shown greyed out, and cannot be selected.
+	pub enabled: bool,
+}
+
+impl<T> MenuItem<T> {
+	fn new(id: T, label: &str, kind: MenuKind) -> MenuItem<T> {
+		MenuItem {
+			id: id,
+			label: label.to_string(),
+			kind: kind,
+			enabled: true,
+		}
+	}
+
+	pub fn label(id: T, label: &str) -> MenuItem<T> {
+		MenuItem::new(id, label, MenuKind::Label)
+	}
+
+	pub fn submenu(id: T, label: &str) -> MenuItem<T> {
+		MenuItem::new(id, label, MenuKind::Submenu)
+	}
+
+	pub fn toggle(id: T, label: &str, on: bool) -> MenuItem<T> {
+		MenuItem::new(id, label, MenuKind::Toggle(on))
+	}
+
+	pub fn slider(id: T, label: &str, value: f64, step: f64) -> MenuItem<T> {
+		MenuItem::new(id, label, MenuKind::Slider { value: value, step: step })
+	}
+
+	pub fn choice(id: T, label: &str, options: Vec<String>, selected: usize) -> MenuItem<T> {
+		MenuItem::new(id, label, MenuKind::Choice { options: options, selected: selected })
+	}
+
+	pub fn value(id: T, label: &str, value: &str) -> MenuItem<T> {
+		MenuItem::new(id, label, MenuKind::Value(value.to_string()))
+	}
+
+	pub fn disabled(mut self) -> MenuItem<T> {
+		self.enabled = false;
+		self
+	}
+
+	/// Changes the value of the item by one step, forward if `dir` is 1 and
+	/// backward if it is -1. Returns whether it changed.
+	fn change(&mut self, dir: i32) -> bool {
+		match self.kind {
+			MenuKind::Toggle(ref mut on) => {
+				*on = !*on;
+				true
+			},
+			MenuKind::Slider { ref mut value, step } => {
+				let old = *value;
+				*value = snap(*value + step * dir as f64, step);
+				//? It stays put at either end of the slider.
+				(*value - old).abs() > ::std::f64::EPSILON
+			},
+			MenuKind::Choice { ref options, ref mut selected } => {
+				if options.is_empty() {
+					return false;
+				}
+				let len = options.len() as i32;
+				*selected = ((*selected as i32 + dir + len) % len) as usize;
+				true
+			},
+			MenuKind::Label | MenuKind::Submenu | MenuKind::Value(_) => false,
+		}
+	}
+
+	/// The text shown on the right of the item, if any. Sliders are drawn
+	/// as bars instead.
+	fn value_text(&self) -> Option<String> {
+		match self.kind {
+			MenuKind::Toggle(on) => Some(if on { "On" } else { "Off" }.to_string()),
+			MenuKind::Choice { ref options, selected } => options.get(selected).cloned(),
+			MenuKind::Submenu => Some(">".to_string()),
+			MenuKind::Value(ref value) => Some(value.clone()),
+			MenuKind::Label | MenuKind::Slider { .. } => None,
+		}
+	}
+}
+
+/// Rounds `value` to a multiple of `step`, between 0 and 1.
+fn snap(value: f64, step: f64) -> f64 {
+	let value = if step > 0.0 { (value / step).round() * step } else { value };
+	value.max(0.0).min(1.0)
+}
+
+/// Where the glyphs of `layout` land when it is drawn at (`x`, `y`), or
+/// `None` if it has none. Unlike the size of the layout, this ignores the
+/// width in which the text was aligned.
+fn text_bounds(layout: &TextLayout, x: f64, y: f64) -> Option<Rectangle> {
+	if layout.glyphs.is_empty() {
+		return None;
+	}
+	let left = layout.glyphs.iter().map(|g| g.dest.x).fold(::std::f64::INFINITY, f64::min);
+	let right = layout.glyphs.iter().map(|g| g.dest.x + g.dest.w).fold(::std::f64::NEG_INFINITY, f64::max);
+
+	Some(Rectangle {
+		x: x + left,
+		y: y,
+		w: right - left,
+		h: layout.h,
+	})
+}
+
+/// The smallest rectangle which holds both `a`, if any, and `b`.
+fn union(a: Option<Rectangle>, b: Rectangle) -> Rectangle {
+	match a {
+		Some(a) => {
+			let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+			Rectangle {
+				x: x,
+				y: y,
+				w: (a.x + a.w).max(b.x + b.w) - x,
+				h: (a.y + a.h).max(b.y + b.h) - y,
+			}
+		},
+		None => b,
+	}
+}
+
+/// What happened to a `Menu` during a frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuEvent<T> {
+	None,
+	/// A label, submenu or value was chosen.
+	Activated(T),
+	/// The value of a toggle, slider or choice was changed.
+	Changed(T),
+	/// The player wants to leave the menu.
+	Back,
+}
+
+/// A list of items in a box, centered in the window, which the player
+/// navigates with the actions of `Phi::input`, the mouse, or by touching
+/// the screen. Items which do not fit in the box are reached by scrolling.
+pub struct Menu<T> {
+	pub items: Vec<MenuItem<T>>,
+	pub theme: MenuTheme,
+	/// How many items fit in the box.
+	pub max_visible: usize,
+	selected: usize,
+	/// The index of the first visible item.
+	scroll: usize,
+	/// Where the label and the control of each visible item were drawn
+	/// during the last frame, to know which one the mouse points at.
+	item_bounds: Vec<(usize, Rectangle)>,
+	/// Where the bar of each visible slider was drawn.
+	slider_bounds: Vec<(usize, Rectangle)>,
+}
+
+impl<T: Copy + PartialEq> Menu<T> {
+	pub fn new(items: Vec<MenuItem<T>>) -> Menu<T> {
+		let mut menu = Menu {
+			items: items,
+			theme: MenuTheme::default(),
+			max_visible: 8,
+			selected: 0,
+			scroll: 0,
+			item_bounds: Vec::new(),
+			slider_bounds: Vec::new(),
+		};
+		//? Start on the first item which can be selected.
+		if !menu.items.is_empty() && !menu.items[0].enabled {
+			menu.move_selection(1);
+		}
+		menu
+	}
+
+	/// The id of the selected item.
+	pub fn selected(&self) -> Option<T> {
+		self.items.get(self.selected).map(|item| item.id)
+	}
+
+	pub fn select(&mut self, id: T) {
+		if let Some(i) = self.items.iter().position(|item| item.id == id) {
+			self.selected = i;
+			self.scroll_to_selected();
+		}
+	}
+
+	pub fn item(&self, id: T) -> Option<&MenuItem<T>> {
+		self.items.iter().find(|item| item.id == id)
+	}
+
+	pub fn item_mut(&mut self, id: T) -> Option<&mut MenuItem<T>> {
+		self.items.iter_mut().find(|item| item.id == id)
+	}
+
+	/// The state of the toggle `id`, or `false` if it is not a toggle.
+	pub fn toggle(&self, id: T) -> bool {
+		match self.item(id).map(|item| &item.kind) {
+			Some(&MenuKind::Toggle(on)) => on,
+			_ => false,
+		}
+	}
+
+	/// The value of the slider `id`, or 0 if it is not a slider.
+	pub fn slider(&self, id: T) -> f64 {
+		match self.item(id).map(|item| &item.kind) {
+			Some(&MenuKind::Slider { value, .. }) => value,
+			_ => 0.0,
+		}
+	}
+
+	/// The index of the option selected by the choice `id`, or 0 if it is
+	/// not a choice.
+	pub fn choice(&self, id: T) -> usize {
+		match self.item(id).map(|item| &item.kind) {
+			Some(&MenuKind::Choice { selected, .. }) => selected,
+			_ => 0,
+		}
+	}
+
+	fn visible_count(&self) -> usize {
+		::std::cmp::min(self.items.len(), self.max_visible)
+	}
+
+	/// Selects the next enabled item in the direction `dir`, looping.
+	fn move_selection(&mut self, dir: i32) {
+		let len = self.items.len() as i32;
+		let mut i = self.selected as i32;
+		for _ in 0..len {
+			i = (i + dir + len) % len;
+			if self.items[i as usize].enabled {
+				self.selected = i as usize;
+				break;
+			}
+		}
+		self.scroll_to_selected();
+	}
+
+	fn scroll_to_selected(&mut self) {
+		let visible = self.visible_count();
+		if self.selected < self.scroll {
+			self.scroll = self.selected;
+		} else if self.selected >= self.scroll + visible {
+			self.scroll = self.selected + 1 - visible;
+		}
+	}
+
+	/// The index of the item drawn at (`x`, `y`) during the last frame.
+	fn item_at(&self, x: f64, y: f64) -> Option<usize> {
+		let point = Rectangle { x: x, y: y, w: 0.0, h: 0.0 };
+		self.item_bounds.iter()
+			.find(|&&(_, bounds)| bounds.contains(point))
+			.map(|&(i, _)| i)
+	}
+
+	/// Chooses the selected item, as with the Confirm action.
+	fn activate(&mut self) -> MenuEvent<T> {
+		let item = &mut self.items[self.selected];
+		match item.kind {
+			MenuKind::Label | MenuKind::Submenu | MenuKind::Value(_) => MenuEvent::Activated(item.id),
+			MenuKind::Slider { .. } => MenuEvent::None,
+			_ => {
+				item.change(1);
+				MenuEvent::Changed(item.id)
+			},
+		}
+	}
+
+	/// Reacts to a click or a tap at `x` on the selected item. Clicking the
+	/// bar of a slider sets its value; anything else activates the item.
+	fn click(&mut self, x: f64) -> MenuEvent<T> {
+		let bar = self.slider_bounds.iter()
+			.find(|&&(i, _)| i == self.selected)
+			.map(|&(_, bar)| bar);
+
+		//? `Some(changed)` if the item is a slider, which is only changed by
+		//? clicking its bar.
+		let slider = match (bar, &mut self.items[self.selected].kind) {
+			(Some(bar), &mut MenuKind::Slider { ref mut value, step }) => {
+				let on_bar = x >= bar.x && x <= bar.x + bar.w;
+				if on_bar {
+					*value = snap((x - bar.x) / bar.w, step);
+				}
+				Some(on_bar)
+			},
+			_ => None,
+		};
+
+		match slider {
+			Some(true) => MenuEvent::Changed(self.items[self.selected].id),
+			Some(false) => MenuEvent::None,
+			None => self.activate(),
+		}
+	}
+
+	/// Handles the input of this frame, and reports what the player did.
+	pub fn update(&mut self, phi: &Phi) -> MenuEvent<T> {
+		if self.items.is_empty() {
+			return MenuEvent::None;
+		}
+
+		let input = &phi.input;
+		let now = &phi.events.now;
+
+		if input.pressed(Action::Back) {
+			return MenuEvent::Back;
+		}
+
+		// The wheel scrolls without changing the selection.
+		let max_scroll = self.items.len() - self.visible_count();
+		let scroll = self.scroll as i32 - now.mouse.wheel.1;
+		self.scroll = ::std::cmp::max(0, ::std::cmp::min(scroll, max_scroll as i32)) as usize;
+
+		// Select the item under the mouse, and choose it when clicked. A tap
+		// on a touch screen is handled like a click.
+		let (mouse_x, mouse_y) = (phi.events.mouse.x, phi.events.mouse.y);
+		let hovered = self.item_at(mouse_x, mouse_y);
+		let tapped = now.touches_started.iter()
+			.filter_map(|&(x, y)| self.item_at(x, y).map(|i| (i, x)))
+			.next();
+
+		if let Some(i) = hovered {
+			if now.mouse.moved && self.items[i].enabled {
+				self.selected = i;
+			}
+		}
+
+		let clicked =
+			if now.mouse.left == Some(true) { hovered.map(|i| (i, mouse_x)) }
+			else { None };
+		if let Some((i, x)) = clicked.or(tapped) {
+			if self.items[i].enabled {
+				self.selected = i;
+				return self.click(x);
+			}
+		}
+
+		if input.repeated(Action::MoveUp) {
+			self.move_selection(-1);
+		}
+		if input.repeated(Action::MoveDown) {
+			self.move_selection(1);
+		}
+
+		if !self.items[self.selected].enabled {
+			return MenuEvent::None;
+		}
+
+		if input.pressed(Action::Confirm) {
+			return self.activate();
+		}
+
+		let dir =
+			if input.repeated(Action::MoveLeft) { -1 }
+			else if input.repeated(Action::MoveRight) { 1 }
+			else { 0 };
+		if dir != 0 && self.items[self.selected].change(dir) {
+			return MenuEvent::Changed(self.items[self.selected].id);
+		}
+
+		MenuEvent::None
+	}
+
+	/// The box drawn by `render`, border included, e.g. to place a title
+	/// above it.
+	pub fn bounds(&self, phi: &Phi) -> Rectangle {
+		let (win_w, win_h) = phi.output_size();
+		let theme = &self.theme;
+		let box_h = self.visible_count() as f64 * theme.item_h;
+
+		Rectangle {
+			w: theme.width + theme.border_width * 2.0,
+			h: box_h + theme.border_width * 2.0 + theme.margin * 2.0,
+			x: (win_w as f64 - theme.width) / 2.0 - theme.border_width,
+			y: (win_h as f64 - box_h) / 2.0 - theme.margin - theme.border_width,
+		}
+	}
+
+	pub fn render(&mut self, phi: &mut Phi) {
+		let theme = self.theme;
+		let outer = self.bounds(phi);
+		let box_x = outer.x + theme.border_width;
+		let box_y = outer.y + theme.border_width;
+		let box_w = theme.width;
+		let box_h = outer.h - theme.border_width * 2.0;
+
+		// Render the border of the coloured box
+		phi.renderer.set_draw_color(theme.border);
+		phi.renderer.fill_rect(outer.to_sdl().unwrap());
+
+		// Render the coloured box which holds the items
+		phi.renderer.set_draw_color(theme.background);
+		phi.renderer.fill_rect(Rectangle {
+			x: box_x,
+			y: box_y,
+			w: box_w,
+			h: box_h,
+		}.to_sdl().unwrap());
+
+		self.item_bounds.clear();
+		self.slider_bounds.clear();
+
+		let visible = self.visible_count();
+		let inner_w = box_w - theme.padding * 2.0;
+
+		for i in self.scroll..self.scroll + visible {
+			let y = box_y + theme.margin + theme.item_h * (i - self.scroll) as f64;
+			//? Only what is drawn can be pointed at, not the empty space of
+			//? the row.
+			let mut hit = None;
+
+			let item = &self.items[i];
+			let (size, color) =
+				if !item.enabled { (theme.size, theme.disabled_text) }
+				else if i == self.selected { (theme.selected_size, theme.selected_text) }
+				else { (theme.size, theme.text) };
+
+			//? Items which show a value have their label on the left and the
+			//? value on the right; the others follow the theme.
+			let has_value = match item.kind {
+				MenuKind::Label => false,
+				_ => true,
+			};
+
+			let (label_x, label_options) =
+				if has_value { (box_x + theme.padding, TextOptions::aligned(Align::Left, inner_w)) }
+				else { (box_x, TextOptions::aligned(theme.align, box_w)) };
+			if let Some(layout) = phi.layout_text(&item.label, theme.font, size, &label_options) {
+				let label_y = y + (theme.item_h - layout.h) / 2.0;
+				phi.renderer.copy_text(&layout, label_x, label_y, color);
+				if let Some(bounds) = text_bounds(&layout, label_x, label_y) {
+					hit = Some(union(hit, bounds));
+				}
+			}
+
+			if let MenuKind::Slider { value, .. } = item.kind {
+				let bar = Rectangle {
+					x: box_x + box_w - theme.padding - box_w * 0.4,
+					y: y + (theme.item_h - 12.0) / 2.0,
+					w: box_w * 0.4,
+					h: 12.0,
+				};
+				self.slider_bounds.push((i, bar));
+				hit = Some(union(hit, bar));
+
+				phi.renderer.set_draw_color(theme.border);
+				phi.renderer.fill_rect(bar.to_sdl().unwrap());
+				//? A fill narrower than a pixel has no SDL rectangle.
+				if let Some(fill) = (Rectangle { w: bar.w * value, .. bar }).to_sdl() {
+					phi.renderer.set_draw_color(if item.enabled { theme.slider } else { color });
+					phi.renderer.fill_rect(fill);
+				}
+			}
+
+			if let Some(value) = item.value_text() {
+				let options = TextOptions::aligned(Align::Right, inner_w);
+				if let Some(layout) = phi.layout_text(&value, theme.font, size, &options) {
+					let (value_x, value_y) = (box_x + theme.padding, y + (theme.item_h - layout.h) / 2.0);
+					phi.renderer.copy_text(&layout, value_x, value_y, color);
+					if let Some(bounds) = text_bounds(&layout, value_x, value_y) {
+						hit = Some(union(hit, bounds));
+					}
+				}
+			}
+
+			if let Some(hit) = hit {
+				self.item_bounds.push((i, hit));
+			}
+		}
+
+		// Show that there are more items above or below.
+		let more_options = TextOptions::aligned(Align::Center, box_w);
+		let (more_above, more_below) = (self.scroll > 0, self.scroll + visible < self.items.len());
+		for &(more, text, y) in &[(more_above, "^", box_y), (more_below, "v", box_y + box_h - theme.margin)] {
+			if more {
+				if let Some(layout) = phi.layout_text(text, theme.font, theme.size / 2, &more_options) {
+					phi.renderer.copy_text(&layout, box_x, y, theme.selected_text);
+				}
+			}
+		}
+	}
+}